@@ -0,0 +1,111 @@
+//! Compare Monte Carlo tree search against the default search on a corpus of deals
+//!
+//! Run with:
+//!     cargo run --release --example benchmark -- [corpus file]
+//!
+//! Each line of the corpus is a board and a stack separated by whitespace, in the same format the
+//! CLI takes. Empty lines and lines starting with # are skipped. Without a corpus, 20 random
+//! deals are used.
+use anyhow::Result;
+use pyrasol::validators::validate_board;
+use pyrasol::{
+    mcts_search, parse_board, read_corpus, simulate_games, Board, Heuristics, Playout, SearchStats,
+    Solution, SolverConfig, Verbosity,
+};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use std::time::Duration;
+
+const MAX_DEPTH: usize = 60;
+
+#[derive(Default)]
+struct Totals {
+    solved: usize,
+    moves: i32,
+    time: Duration,
+}
+
+impl Totals {
+    fn add(&mut self, solution: &Solution, stats: &SearchStats) {
+        if !solution.is_partial() {
+            self.solved += 1;
+            self.moves += solution.moves;
+        }
+        self.time += stats.elapsed;
+    }
+
+    fn print(&self, name: &str, deals: usize) {
+        let average_moves = if self.solved > 0 {
+            self.moves as f64 / self.solved as f64
+        } else {
+            0.0
+        };
+        println!(
+            "{:<20} {:>3}/{:<3} solved - {:>5.1} moves on average - {:>8.2?} in total",
+            name, self.solved, deals, average_moves, self.time
+        );
+    }
+}
+
+fn random_deals(count: usize) -> Result<Vec<Board>> {
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut cards: Vec<char> = "a234567890jqk".chars().cycle().take(52).collect();
+
+    (0..count)
+        .map(|_| {
+            cards.shuffle(&mut rng);
+            let (board_cards, stack_cards) =
+                parse_board(cards[..28].iter().collect(), cards[28..].iter().collect())?;
+            validate_board(&board_cards, &stack_cards)?;
+            let leaf_idxs: Vec<usize> = vec![21, 22, 23, 24, 25, 26, 27];
+            Ok(Board::new(board_cards, stack_cards, leaf_idxs, false))
+        })
+        .collect()
+}
+
+fn main() -> Result<()> {
+    let deals = match std::env::args().nth(1) {
+        Some(path) => read_corpus(&std::fs::read_to_string(path)?)?,
+        None => random_deals(20)?,
+    };
+
+    let mut bfs = Totals::default();
+    let mut mcts_random = Totals::default();
+    let mut mcts_heuristic = Totals::default();
+
+    let mut rng = StdRng::seed_from_u64(0);
+    for board in deals.iter() {
+        let (outcome, stats) = simulate_games(
+            board.clone(),
+            MAX_DEPTH,
+            &Heuristics::DEFAULT,
+            &SolverConfig::default(),
+            Verbosity::Off,
+        )?;
+        bfs.add(&outcome.into_solution(), &stats);
+
+        for (totals, playout) in [
+            (&mut mcts_random, Playout::Random),
+            (&mut mcts_heuristic, Playout::Heuristic),
+        ] {
+            let (solution, stats) = mcts_search(
+                board.clone(),
+                MAX_DEPTH,
+                500,
+                1.4,
+                playout,
+                &mut rng,
+                Verbosity::Off,
+            );
+            totals.add(&solution, &stats);
+        }
+    }
+
+    println!();
+    bfs.print("BFS", deals.len());
+    mcts_random.print("MCTS (random)", deals.len());
+    mcts_heuristic.print("MCTS (heuristic)", deals.len());
+
+    Ok(())
+}
@@ -1,7 +1,9 @@
 use crate::game::blocks::{card_blocked_by, card_blocks, card_directly_blocks};
 use crate::game::card::{Card, MatchType, RawCard};
+use crate::game::codec::{decode_card, encode_card, unique_cards};
 use crate::game::r#move::{move_sort, Move};
-use crate::game::utils::{cards_match, match_card};
+use crate::game::utils::{cards_match, match_card, validate_reached_position};
+use anyhow::{bail, Context, Result};
 use std::cmp::max;
 use std::collections::BTreeSet;
 use std::collections::HashSet;
@@ -22,6 +24,18 @@ pub struct Board {
     pub completed: bool,
 }
 
+/// What a move changed on the board, so that it can be taken back with `Board::undo_move`
+#[derive(Debug, Clone)]
+pub struct Undo {
+    r#move: Move,
+    leaf_idxs: BTreeSet<usize>,
+    stack_idx: i32,
+    moves: i32,
+    completed: bool,
+    /// Cards taken off the stack, with their position at the time, in the order they were taken
+    stack_cards: Vec<(usize, RawCard)>,
+}
+
 impl Board {
     pub fn new(
         cards: Vec<RawCard>,
@@ -55,6 +69,148 @@ impl Board {
         }
     }
 
+    /// A board for a game in progress, as parsed by `parse_position`
+    ///
+    /// The stack only has the cards left in it, with `stack_idx` cards of it already drawn. Removed
+    /// board cards are filled in with cards that are no longer in the game, so that every card of
+    /// the board stays unique. The cards need to pass `validate_position`, or there may not be
+    /// enough cards to fill in.
+    pub fn from_position(
+        cards: &[Option<RawCard>],
+        stack: Vec<RawCard>,
+        stack_idx: i32,
+        moves: i32,
+        clear_all: bool,
+    ) -> Board {
+        let mut card_counts = [0; 13];
+        for raw_card in cards.iter().flatten().chain(stack.iter()) {
+            card_counts[(raw_card.0 % 13) as usize] += 1;
+        }
+
+        let mut gone = (0..52)
+            .map(RawCard)
+            .filter(|card| !cards.contains(&Some(*card)) && !stack.contains(card));
+        let board_cards: Vec<RawCard> = cards
+            .iter()
+            .map(|card| card.unwrap_or_else(|| gone.next().unwrap()))
+            .collect();
+
+        let leaf_idxs: Vec<usize> = (0..28)
+            .filter(|&idx| cards[idx].is_some())
+            .filter(|&idx| {
+                card_blocked_by(idx)
+                    .into_iter()
+                    .all(|other| cards[other].is_none())
+            })
+            .collect();
+
+        // Only a won game has the top of the pyramid removed, unless the stack is still to clear
+        let completed = cards[0].is_none() && (!clear_all || stack.is_empty());
+        Board {
+            card_counts,
+            stack_idx,
+            moves,
+            completed,
+            ..Board::new(board_cards, stack, leaf_idxs, clear_all)
+        }
+    }
+
+    /// The position as a string that `from_canonical` reads back into the same position
+    ///
+    /// The board, the stack left, the number of stack cards drawn and the moves made, separated by
+    /// /, with cards written like `AS` and cards removed from the board as `.`. Boards that clear
+    /// the stack as well end with `/ all`.
+    pub fn to_canonical(&self) -> String {
+        let remaining = self.remaining_idxs();
+        let board: Vec<String> = (0..28)
+            .map(|idx| {
+                if remaining.contains(&idx) {
+                    encode_card(self.board_cards[idx], false)
+                } else {
+                    ".".to_string()
+                }
+            })
+            .collect();
+        let stack: Vec<String> = self
+            .stack
+            .iter()
+            .map(|&card| encode_card(card, false))
+            .collect();
+
+        let mut canonical = format!(
+            "{} / {} / {} / {}",
+            board.join(" "),
+            stack.join(" "),
+            self.stack_idx,
+            self.moves
+        );
+        if self.clear_all {
+            canonical.push_str(" / all");
+        }
+        canonical
+    }
+
+    /// Read a position written by `to_canonical`
+    pub fn from_canonical(canonical: &str) -> Result<Board> {
+        let parts: Vec<&str> = canonical.split('/').map(str::trim).collect();
+        let clear_all = match parts[..] {
+            [_, _, _, _] => false,
+            [_, _, _, _, "all"] => true,
+            _ => bail!("A position needs the board, stack, stack cards drawn and moves made, separated by /"),
+        };
+
+        let board_cards: Vec<Option<RawCard>> = parts[0]
+            .split_whitespace()
+            .map(|token| match token {
+                "." => Ok(None),
+                _ => decode_card(token).map(Some),
+            })
+            .collect::<Result<_>>()?;
+        let stack: Vec<RawCard> = parts[1]
+            .split_whitespace()
+            .map(decode_card)
+            .collect::<Result<_>>()?;
+        let stack_idx: usize = parts[2]
+            .parse()
+            .with_context(|| format!("Invalid number of stack cards drawn: {}", parts[2]))?;
+        let moves: i32 = parts[3]
+            .parse()
+            .with_context(|| format!("Invalid number of moves made: {}", parts[3]))?;
+
+        Board::checked_position(&board_cards, stack, stack_idx, moves, clear_all)
+    }
+
+    /// `from_position`, failing if the cards don't make a position that can come up in a game
+    fn checked_position(
+        board_cards: &[Option<RawCard>],
+        stack: Vec<RawCard>,
+        stack_idx: usize,
+        moves: i32,
+        clear_all: bool,
+    ) -> Result<Board> {
+        if moves < 0 {
+            bail!(
+                "The number of moves made can't be negative, but it is {}",
+                moves
+            )
+        }
+        for card in board_cards.iter().flatten().chain(stack.iter()) {
+            RawCard::try_from(card.0)?;
+        }
+        unique_cards(
+            board_cards.iter().flatten().copied().collect(),
+            stack.clone(),
+        )?;
+        validate_reached_position(board_cards, &stack, stack_idx)?;
+        Ok(Board::from_position(
+            board_cards,
+            stack,
+            stack_idx as i32,
+            moves,
+            clear_all,
+        ))
+    }
+
     pub fn get_state(&self) -> Vec<u8> {
         let card_state: Vec<u8> = self
             .leaf_idxs
@@ -68,6 +224,59 @@ impl Board {
         card_state
     }
 
+    /// The state of the board without the move count, for boards that are the same position
+    /// reached in a different number of moves
+    pub(crate) fn get_position(&self) -> Vec<u8> {
+        self.leaf_idxs
+            .iter()
+            .map(|&idx| idx as u8)
+            .chain(vec![0u8])
+            .chain(self.stack.iter().map(|card| card.0))
+            .chain(vec![0u8, self.stack_idx as u8])
+            .collect()
+    }
+
+    /// Everything that changes as moves are played, packed into bytes
+    ///
+    /// The board can be rebuilt from it with `restore_snapshot`, given any board of the same deal
+    pub(crate) fn snapshot(&self) -> Vec<u8> {
+        let mut snapshot = vec![self.leaf_idxs.len() as u8];
+        snapshot.extend(self.leaf_idxs.iter().map(|&idx| idx as u8));
+        snapshot.push(self.stack.len() as u8);
+        snapshot.extend(self.stack.iter().map(|card| card.0));
+        snapshot.push(self.stack_idx as u8);
+        snapshot.extend((self.moves as u16).to_le_bytes());
+        snapshot.push(self.completed as u8);
+        snapshot.extend(self.card_counts);
+        snapshot
+    }
+
+    /// Rebuild a board of the same deal from a `snapshot`
+    pub(crate) fn restore_snapshot(&self, snapshot: &[u8]) -> Board {
+        let (&leaf_count, rest) = snapshot.split_first().unwrap();
+        let (leaf_idxs, rest) = rest.split_at(leaf_count as usize);
+        let (&stack_len, rest) = rest.split_first().unwrap();
+        let (stack, rest) = rest.split_at(stack_len as usize);
+
+        let stack: Vec<RawCard> = stack.iter().map(|&card| RawCard(card)).collect();
+        let mut stack_counts = [0; 13];
+        for raw_card in &stack {
+            stack_counts[(raw_card.0 % 13) as usize] += 1;
+        }
+
+        Board {
+            board_cards: self.board_cards,
+            card_counts: rest[4..17].try_into().unwrap(),
+            stack,
+            stack_idx: rest[0] as i32,
+            stack_counts,
+            leaf_idxs: leaf_idxs.iter().map(|&idx| idx as usize).collect(),
+            moves: u16::from_le_bytes([rest[1], rest[2]]) as i32,
+            clear_all: self.clear_all,
+            completed: rest[3] == 1,
+        }
+    }
+
     pub fn remove_cards(&mut self, (left, right): (RawCard, Option<RawCard>)) {
         // Get the indexes of the cards we are going to remove
         let mut card_idxs: Vec<usize> = vec![self
@@ -132,7 +341,7 @@ impl Board {
         for raw_card in self.leaves() {
             let card: Card = raw_card.into();
             if card.0 == 13 {
-                return vec![(MatchType::Board, 0, (raw_card, None))];
+                return vec![Move::new(MatchType::Board, 0, raw_card, None)];
             }
         }
 
@@ -171,9 +380,9 @@ impl Board {
                 already_matched.insert(leaf);
                 if solo_cards.contains(&leaf.into()) {
                     // Last pair match, only logical move
-                    return vec![(MatchType::Board, 0, (leaf, Some(potential_match)))];
+                    return vec![Move::new(MatchType::Board, 0, leaf, Some(potential_match))];
                 }
-                moves.push((MatchType::Board, 0, (leaf, Some(potential_match))));
+                moves.push(Move::new(MatchType::Board, 0, leaf, Some(potential_match)));
                 moves_on_table = true;
             }
         }
@@ -198,18 +407,20 @@ impl Board {
                 let stack_card = self.stack[stack_card_idx as usize];
                 if solo_cards.contains(&leaf_val) && draw <= 0 {
                     // We should get rif og it ASAP
-                    return vec![(
+                    return vec![Move::new(
                         MatchType::BoardStack,
                         max(draw, 0),
-                        (leaf, Some(stack_card)),
+                        leaf,
+                        Some(stack_card),
                     )];
                 }
 
                 // Left side of visible stack card is -1, no need to draw, hence the max
-                moves.push((
+                moves.push(Move::new(
                     MatchType::BoardStack,
                     max(draw, 0),
-                    (leaf, Some(stack_card)),
+                    leaf,
+                    Some(stack_card),
                 ));
             }
         }
@@ -219,9 +430,11 @@ impl Board {
         if !moves_on_table {
             if let Some(pot_move) = stack_moves
                 .iter()
-                .filter(|(_, draws, _)| *draws == 0)
+                .filter(|Move { draws, .. }| *draws == 0)
                 .find_map(|r#move| {
-                    let (_, _, (left_card, _)) = r#move;
+                    let Move {
+                        left: left_card, ..
+                    } = r#move;
                     let card: Card = (*left_card).into();
                     if card.0 == 13 || solo_cards.contains(&card) {
                         Some(vec![*r#move])
@@ -285,12 +498,12 @@ impl Board {
             if self.stack_idx < self.stack.len() as i32 {
                 let right = self.stack[self.stack_idx as usize];
                 if cards_match(left, right) {
-                    moves.insert((MatchType::Stack, 0, (left, Some(right))));
+                    moves.insert(Move::new(MatchType::Stack, 0, left, Some(right)));
                 }
             }
             // Also check if there is a king visible on the left side
             if Card::from(left).0 == 13 {
-                moves.insert((MatchType::Stack, -1, (left, None)));
+                moves.insert(Move::new(MatchType::Stack, -1, left, None));
             }
         }
 
@@ -298,7 +511,7 @@ impl Board {
         if self.stack_idx < self.stack.len() as i32 {
             let right = self.stack[self.stack_idx as usize];
             if Card::from(right).0 == 13 {
-                moves.insert((MatchType::Stack, 0, (right, None)));
+                moves.insert(Move::new(MatchType::Stack, 0, right, None));
             }
         }
 
@@ -310,9 +523,14 @@ impl Board {
         {
             if Card::from(*right).0 == 13 {
                 // Get rid of that king!
-                moves.insert((MatchType::Stack, draw as i32 + 1, (*right, None)));
+                moves.insert(Move::new(MatchType::Stack, draw as i32 + 1, *right, None));
             } else if cards_match(*left, *right) {
-                moves.insert((MatchType::Stack, draw as i32 + 1, (*left, Some(*right))));
+                moves.insert(Move::new(
+                    MatchType::Stack,
+                    draw as i32 + 1,
+                    *left,
+                    Some(*right),
+                ));
             }
         }
 
@@ -324,10 +542,11 @@ impl Board {
             .enumerate()
         {
             if cards_match(*left, *right) {
-                moves.insert((
+                moves.insert(Move::new(
                     MatchType::Stack,
                     draw as i32 + stack_len as i32 - self.stack_idx + 1,
-                    (*left, Some(*right)),
+                    *left,
+                    Some(*right),
                 ));
             }
         }
@@ -380,15 +599,159 @@ impl Board {
         self.moves += draws;
     }
 
+    /// Number of cards that still need to be cleared, which includes the stack when clearing all
+    pub fn cards_left(&self) -> usize {
+        let board_cards = self.remaining_idxs().len();
+        if self.clear_all {
+            board_cards + self.stack.len()
+        } else {
+            board_cards
+        }
+    }
+
+    /// Indexes of the cards still on the board, which are the leaves and every card they cover
+    pub(crate) fn remaining_idxs(&self) -> BTreeSet<usize> {
+        let mut idxs = self.leaf_idxs.clone();
+        for leaf_idx in self.leaf_idxs.iter() {
+            idxs.extend(card_blocks(*leaf_idx));
+        }
+        idxs
+    }
+
+    /// A lower bound on the moves, including draws, still needed to clear the board
+    ///
+    /// The bound is the larger of two:
+    /// * Every move clears at most two cards, and kings take a move of their own. When clearing
+    ///   the stack as well, kings left of the stack pointer are removed without using up a move
+    /// * A card on the board that can't be matched with anything else on the board needs a card
+    ///   from the stack. Every card in the way has to be drawn past, or removed two at a time at
+    ///   best, before the match can be made
+    pub fn lower_bound_moves(&self) -> i32 {
+        if self.completed {
+            return 0;
+        }
+
+        let remaining = self.remaining_idxs();
+        let mut cards: Vec<RawCard> = remaining.iter().map(|&idx| self.board_cards[idx]).collect();
+        if self.clear_all {
+            cards.extend(self.stack.iter());
+        }
+        let kings = cards
+            .iter()
+            .filter(|&&card| Card::from(card).0 == 13)
+            .count() as i32;
+        let pairs = (cards.len() as i32 - kings + 1) / 2;
+        // Removing the king left of the stack pointer uncovers the card before it, which can be
+        // another king
+        let free_kings = if self.clear_all {
+            self.stack[..self.stack_idx as usize]
+                .iter()
+                .rev()
+                .take_while(|&&card| Card::from(card).0 == 13)
+                .count() as i32
+        } else {
+            0
+        };
+
+        let stack_reach = remaining
+            .iter()
+            .filter(|&&idx| Card::from(self.board_cards[idx]).0 != 13)
+            .filter(|&&idx| {
+                // No partner left on the board that it could ever be matched with
+                !remaining.iter().any(|&other| {
+                    cards_match(self.board_cards[idx], self.board_cards[other])
+                        && !card_blocked_by(idx).contains(&other)
+                        && !card_blocked_by(other).contains(&idx)
+                })
+            })
+            .filter_map(|&idx| {
+                let partner = match_card(self.board_cards[idx].into());
+                self.get_stack_draws(partner)
+                    .into_iter()
+                    .map(|draws| max(draws, 0))
+                    .min()
+            })
+            .map(|draws| (draws + 1) / 2 + 1)
+            .max()
+            .unwrap_or(0);
+
+        max(kings - free_kings + pairs, stack_reach)
+    }
+
+    /// Check if the board can be shown to be impossible to clear
+    ///
+    /// Every card has to be removed together with a matching card, and two cards can't be matched
+    /// while one of them covers the other. For each pair of matching ranks, this checks if all the
+    /// cards that need clearing can get their own partner that way. Kings are removed on their own
+    /// and the stack can be cycled through as often as needed, so neither can make a board dead.
+    pub fn is_dead(&self) -> bool {
+        self.dead_rank().is_some()
+    }
+
+    /// The lower rank of the first pair of matching ranks that can't all be paired up, if any
+    ///
+    /// See `is_dead` for how that's checked
+    pub(crate) fn dead_rank(&self) -> Option<u8> {
+        if self.completed {
+            return None;
+        }
+
+        let remaining = self.remaining_idxs();
+
+        // The cards of a rank, with their index if they are on the board
+        let cards_of_rank = |rank: u8| -> Vec<Option<usize>> {
+            remaining
+                .iter()
+                .filter(|&&idx| Card::from(self.board_cards[idx]).0 == rank)
+                .map(|&idx| Some(idx))
+                .chain(
+                    self.stack
+                        .iter()
+                        .filter(|&&card| Card::from(card).0 == rank)
+                        .map(|_| None),
+                )
+                .collect()
+        };
+        let compatible = |a: Option<usize>, b: Option<usize>| match (a, b) {
+            (Some(a), Some(b)) => {
+                !card_blocked_by(a).contains(&b) && !card_blocked_by(b).contains(&a)
+            }
+            _ => true,
+        };
+
+        for rank in 1..=6 {
+            let cards = cards_of_rank(rank);
+            let partners = cards_of_rank(13 - rank);
+
+            // If both sides can be paired up on their own, they can be paired up together
+            for (cards, partners) in [(&cards, &partners), (&partners, &cards)] {
+                let required: Vec<usize> = (0..cards.len())
+                    .filter(|&idx| cards[idx].is_some() || self.clear_all)
+                    .collect();
+                let can_pair =
+                    |card: usize, partner: usize| compatible(cards[card], partners[partner]);
+                if !can_pair_all(&required, partners.len(), &can_pair, 0) {
+                    return Some(rank);
+                }
+            }
+        }
+
+        None
+    }
+
+    /// The stack has to be cleared as well to win
+    pub(crate) fn clears_all(&self) -> bool {
+        self.clear_all
+    }
+
     pub(crate) fn leaves(&self) -> BTreeSet<RawCard> {
         BTreeSet::from_iter(self.leaf_idxs.iter().map(|idx| self.board_cards[*idx]))
     }
 
     pub fn play_move(&mut self, r#move: Move) {
-        let (move_type, draws, cards) = r#move;
-        self.stack_draw(draws);
+        self.stack_draw(r#move.draws);
 
-        match (move_type, cards) {
+        match (r#move.match_type, r#move.cards()) {
             (MatchType::Board, cards) => self.remove_cards(cards),
             (MatchType::BoardStack, (board_card, Some(stack_card))) => {
                 // Should raise value error if flipped, not wasting cycles on error checking,
@@ -417,11 +780,152 @@ impl Board {
 
         self.moves += 1;
     }
+
+    /// Play a move, returning what's needed to take it back again
+    pub fn play_move_with_undo(&mut self, r#move: Move) -> Undo {
+        let Move {
+            match_type,
+            left,
+            right,
+            ..
+        } = r#move;
+        let taken: Vec<RawCard> = match (match_type, right) {
+            (MatchType::Board, _) => vec![],
+            (MatchType::BoardStack, Some(stack_card)) => vec![stack_card],
+            (MatchType::Stack, right) => std::iter::once(left).chain(right).collect(),
+            _ => panic!("Illegal move"),
+        };
+
+        // Positions are taken one card at a time, as removing the first shifts the second
+        let mut stack = self.stack.clone();
+        let stack_cards = taken
+            .into_iter()
+            .map(|card| {
+                let pos = stack.iter().position(|&c| c == card).unwrap();
+                stack.remove(pos);
+                (pos, card)
+            })
+            .collect();
+
+        let undo = Undo {
+            r#move,
+            leaf_idxs: self.leaf_idxs.clone(),
+            stack_idx: self.stack_idx,
+            moves: self.moves,
+            completed: self.completed,
+            stack_cards,
+        };
+        self.play_move(r#move);
+        undo
+    }
+
+    /// Take back the move that returned `undo`, which has to be the last move played
+    pub fn undo_move(&mut self, undo: Undo) {
+        for &(pos, card) in undo.stack_cards.iter().rev() {
+            self.stack.insert(pos, card);
+            self.stack_counts[(card.0 % 13) as usize] += 1;
+        }
+
+        let Move { left, right, .. } = undo.r#move;
+        self.card_counts[(left.0 % 13) as usize] += 1;
+        if let Some(right) = right {
+            self.card_counts[(right.0 % 13) as usize] += 1;
+        }
+
+        self.leaf_idxs = undo.leaf_idxs;
+        self.stack_idx = undo.stack_idx;
+        self.moves = undo.moves;
+        self.completed = undo.completed;
+    }
+}
+
+/// How a board is serialized
+///
+/// * `board` - the 28 cards of the pyramid, top row first, as `RawCard` numbers or `null` for
+///   removed cards
+/// * `stack` - the cards left in the stack
+/// * `stack_position` - how many cards of the stack have been drawn
+/// * `moves_made` - moves made so far, including draws
+/// * `clear_all` - whether the stack has to be cleared as well
+///
+/// This is the same position `to_canonical` writes, and boards `from_canonical` refuses fail to
+/// deserialize the same way.
+///
+/// The JSON report writes its boards the same way.
+#[derive(serde::Serialize)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize),
+    serde(deny_unknown_fields)
+)]
+pub(crate) struct BoardData {
+    pub(crate) board: Vec<Option<RawCard>>,
+    pub(crate) stack: Vec<RawCard>,
+    pub(crate) stack_position: usize,
+    pub(crate) moves_made: i32,
+    pub(crate) clear_all: bool,
+}
+
+impl Board {
+    pub(crate) fn to_data(&self) -> BoardData {
+        let remaining = self.remaining_idxs();
+        BoardData {
+            board: (0..28)
+                .map(|idx| remaining.contains(&idx).then_some(self.board_cards[idx]))
+                .collect(),
+            stack: self.stack.clone(),
+            stack_position: self.stack_idx as usize,
+            moves_made: self.moves,
+            clear_all: self.clear_all,
+        }
+    }
+}
+
+impl serde::Serialize for Board {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_data().serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Board {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Board, D::Error> {
+        let data = BoardData::deserialize(deserializer)?;
+        Board::checked_position(
+            &data.board,
+            data.stack,
+            data.stack_position,
+            data.moves_made,
+            data.clear_all,
+        )
+        .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Check if every card can be given its own partner, out of `partners` possible ones
+///
+/// `used` is a bit mask of the partners that have already been given out
+fn can_pair_all<F: Fn(usize, usize) -> bool>(
+    cards: &[usize],
+    partners: usize,
+    can_pair: &F,
+    used: u64,
+) -> bool {
+    match cards.split_first() {
+        None => true,
+        Some((&card, rest)) => (0..partners).any(|partner| {
+            used & (1 << partner) == 0
+                && can_pair(card, partner)
+                && can_pair_all(rest, partners, can_pair, used | (1 << partner))
+        }),
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::game::utils::parse_board;
+    use crate::test_utils::{readme_board, solve};
 
     fn get_base_board() -> Board {
         let cards: Vec<RawCard> = vec![
@@ -503,6 +1007,135 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_board_from_position() {
+        let (cards, stack) = parse_board(
+            "jj6j88a95k3ka02j4q32k0767qk7".to_string(),
+            "68480a55q69a2339527q4490".to_string(),
+        )
+        .unwrap();
+        let fresh = Board::new(cards.clone(), stack.clone(), (21..28).collect(), false);
+        let known: Vec<Option<RawCard>> = cards.iter().copied().map(Some).collect();
+        let board = Board::from_position(&known, stack.clone(), 0, 0, false);
+        assert_eq!(board.get_state(), fresh.get_state());
+        assert_eq!(board.get_moves(), fresh.get_moves());
+
+        // Play the king on the board and a few draws, then describe the same game as a position
+        let mut played = fresh.clone();
+        let king = played.get_moves()[0];
+        played.play_move(king);
+        played.stack_draw(3);
+
+        let mut known = known;
+        known[26] = None;
+        let board = Board::from_position(&known, stack, 3, 4, false);
+        assert_eq!(board.leaf_idxs, played.leaf_idxs);
+        assert_eq!(board.card_counts, played.card_counts);
+        assert_eq!(board.get_state(), played.get_state());
+        assert_eq!(board.get_moves(), played.get_moves());
+        let unique: HashSet<RawCard> = board
+            .board_cards
+            .iter()
+            .chain(&board.stack)
+            .copied()
+            .collect();
+        assert_eq!(unique.len(), 28 + board.stack.len());
+    }
+
+    #[test]
+    fn test_board_canonical_round_trip() {
+        let mut board = get_base_board();
+        let canonical = board.to_canonical();
+        assert!(canonical.ends_with(" / 0 / 0"));
+        assert_eq!(
+            Board::from_canonical(&canonical).unwrap().to_canonical(),
+            canonical
+        );
+
+        for _ in 0..5 {
+            let r#move = board.get_moves()[0];
+            board.play_move(r#move);
+        }
+        let canonical = board.to_canonical();
+        let read = Board::from_canonical(&canonical).unwrap();
+        assert_eq!(read.to_canonical(), canonical);
+        assert_eq!(read.get_state(), board.get_state());
+        assert_eq!(read.get_moves(), board.get_moves());
+
+        assert!(Board::from_canonical("AS / 2S / 0").is_err());
+        let overfull = format!(
+            "AS{} / {} / 0 / 0",
+            " .".repeat(27),
+            ["H", "D", "C"]
+                .iter()
+                .flat_map(|suit| "23456789T"
+                    .chars()
+                    .map(move |rank| format!("{}{}", rank, suit)))
+                .take(25)
+                .collect::<Vec<_>>()
+                .join(" ")
+        );
+        assert!(Board::from_canonical(&overfull).is_err());
+        assert!(Board::from_canonical(&canonical.replacen(". ", "AS ", 1)).is_err());
+        let (position, _) = canonical.rsplit_once(" / ").unwrap();
+        assert!(Board::from_canonical(&format!("{} / -5", position)).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_board_serde_round_trip() {
+        let mut board = get_base_board();
+        for _ in 0..5 {
+            let r#move = board.get_moves()[0];
+            board.play_move(r#move);
+        }
+
+        let json = serde_json::to_string(&board).unwrap();
+        assert!(json.starts_with("{\"board\":[7,6,"));
+        assert!(json.ends_with(&format!(
+            ",\"stack_position\":{},\"moves_made\":{},\"clear_all\":false}}",
+            board.stack_idx, board.moves
+        )));
+        let read: Board = serde_json::from_str(&json).unwrap();
+        assert_eq!(read.to_canonical(), board.to_canonical());
+        assert_eq!(read.get_moves(), board.get_moves());
+
+        let r#move = board.get_moves()[0];
+        let json = serde_json::to_string(&r#move).unwrap();
+        assert!(json.starts_with("{\"match_type\":\""));
+        assert_eq!(serde_json::from_str::<Move>(&json).unwrap(), r#move);
+        let json = r#"{"match_type":"board","draws":0,"left":3,"right":52}"#;
+        assert!(serde_json::from_str::<Move>(json).is_err());
+
+        let json = serde_json::to_string(&get_base_board()).unwrap();
+        assert!(serde_json::from_str::<Board>(&json.replacen("[7,", "[null,", 1)).is_err());
+        assert!(serde_json::from_str::<Board>(&json.replacen("[7,", "[60,", 1)).is_err());
+    }
+
+    #[test]
+    fn test_completed_board_round_trip() {
+        let mut board = readme_board();
+        for move_num in solve(&board).moves_played {
+            let r#move = board.get_moves()[move_num - 1];
+            board.play_move(r#move);
+        }
+        assert!(board.completed);
+
+        let canonical = board.to_canonical();
+        let read = Board::from_canonical(&canonical).unwrap();
+        assert!(read.completed);
+        assert_eq!(read.to_canonical(), canonical);
+        assert_eq!(read.get_state(), board.get_state());
+
+        #[cfg(feature = "serde")]
+        {
+            let json = serde_json::to_string(&board).unwrap();
+            let read: Board = serde_json::from_str(&json).unwrap();
+            assert!(read.completed);
+            assert_eq!(serde_json::to_string(&read).unwrap(), json);
+        }
+    }
+
     #[test]
     fn test_board_remove_cards() {
         let mut board = get_base_board();
@@ -556,12 +1189,12 @@ mod test {
 
         assert_eq!(moves.len(), 1);
 
-        let (move_type, draws, cards) = &moves[0];
+        let r#move = moves[0];
 
         // Only one move makes sense, the king (12) in the leaves
-        assert_eq!(*move_type, MatchType::Board);
-        assert_eq!(*draws, 0);
-        assert_eq!(*cards, (RawCard(12), None));
+        assert_eq!(r#move.match_type, MatchType::Board);
+        assert_eq!(r#move.draws, 0);
+        assert_eq!(r#move.cards(), (RawCard(12), None));
 
         // Remove the king
         board.remove_cards((RawCard(12), None));
@@ -572,21 +1205,21 @@ mod test {
         // There should be exactly 3 Board matches
         let board_matches = moves
             .iter()
-            .filter(|r#move| r#move.0 == MatchType::Board)
+            .filter(|r#move| r#move.match_type == MatchType::Board)
             .count();
         assert_eq!(board_matches, 3);
 
         // There should be exactly 12 BoardStack matches
         let board_stack_matches = moves
             .iter()
-            .filter(|r#move| r#move.0 == MatchType::BoardStack)
+            .filter(|r#move| r#move.match_type == MatchType::BoardStack)
             .count();
         assert_eq!(board_stack_matches, 12);
 
         // And finally there should be exactly 5 Stack matches
         let stack_matches = moves
             .iter()
-            .filter(|r#move| r#move.0 == MatchType::Stack)
+            .filter(|r#move| r#move.match_type == MatchType::Stack)
             .count();
         assert_eq!(stack_matches, 5);
 
@@ -610,6 +1243,46 @@ mod test {
         assert_eq!(moves.len(), 20);
     }
 
+    #[test]
+    fn test_board_undo_move_and_snapshot() {
+        let mut board = get_base_board();
+        let mut history = vec![];
+
+        // Alternate between the first and last move, to get board, stack and mixed matches in
+        while !board.completed && history.len() < 20 {
+            let moves = board.get_moves();
+            if moves.is_empty() {
+                break;
+            }
+            let r#move = if history.len() % 2 == 0 {
+                moves[0]
+            } else {
+                moves[moves.len() - 1]
+            };
+            let before = (board.get_state(), board.card_counts, board.stack_counts);
+            history.push((before, board.play_move_with_undo(r#move)));
+        }
+        assert!(history.len() > 5);
+
+        let restored = get_base_board().restore_snapshot(&board.snapshot());
+        assert_eq!(
+            (
+                restored.get_state(),
+                restored.card_counts,
+                restored.stack_counts
+            ),
+            (board.get_state(), board.card_counts, board.stack_counts)
+        );
+
+        while let Some((before, undo)) = history.pop() {
+            board.undo_move(undo);
+            assert_eq!(
+                (board.get_state(), board.card_counts, board.stack_counts),
+                before
+            );
+        }
+    }
+
     #[test]
     fn test_board_get_moves_has_a_stable_order() {
         let mut board_a = get_base_board();
@@ -701,24 +1374,153 @@ mod test {
         let stack_moves: Vec<Move> = board.get_stack_moves().into_iter().collect::<Vec<_>>();
         assert_eq!(
             stack_moves,
-            vec![(MatchType::Stack, 3, (RawCard(51), None))]
+            vec![Move::new(MatchType::Stack, 3, RawCard(51), None)]
         );
 
         let moves = board.get_moves();
         assert_eq!(
             moves,
             vec![
-                (MatchType::Stack, 3, (RawCard(51), None)),
-                (MatchType::BoardStack, 4, (RawCard(2), Some(RawCard(48)))),
-                (MatchType::BoardStack, 7, (RawCard(8), Some(RawCard(42)))),
-                (MatchType::BoardStack, 10, (RawCard(8), Some(RawCard(3)))),
-                (MatchType::BoardStack, 13, (RawCard(8), Some(RawCard(16)))),
-                (MatchType::BoardStack, 14, (RawCard(8), Some(RawCard(29)))),
-                (MatchType::BoardStack, 15, (RawCard(19), Some(RawCard(44))))
+                Move::new(MatchType::Stack, 3, RawCard(51), None),
+                Move::new(MatchType::BoardStack, 4, RawCard(2), Some(RawCard(48))),
+                Move::new(MatchType::BoardStack, 7, RawCard(8), Some(RawCard(42))),
+                Move::new(MatchType::BoardStack, 10, RawCard(8), Some(RawCard(3))),
+                Move::new(MatchType::BoardStack, 13, RawCard(8), Some(RawCard(16))),
+                Move::new(MatchType::BoardStack, 14, RawCard(8), Some(RawCard(29))),
+                Move::new(MatchType::BoardStack, 15, RawCard(19), Some(RawCard(44)))
             ]
         );
     }
 
+    #[test]
+    fn test_cards_left() {
+        let mut board = get_base_board();
+        assert_eq!(board.cards_left(), 28);
+
+        // The king on the board
+        board.play_move(Move::new(MatchType::Board, 0, RawCard(12), None));
+        assert_eq!(board.cards_left(), 27);
+
+        // A stack match doesn't clear anything on the board
+        let r#move: Move = board
+            .get_moves()
+            .into_iter()
+            .find(|r#move| r#move.match_type == MatchType::Stack)
+            .unwrap();
+        board.play_move(r#move);
+        assert_eq!(board.cards_left(), 27);
+
+        board.clear_all = true;
+        assert_eq!(board.cards_left(), 27 + board.stack.len());
+    }
+
+    #[test]
+    fn test_remaining_idxs() {
+        let mut board = get_base_board();
+        assert_eq!(board.remaining_idxs(), (0..28).collect());
+
+        board.remove_cards((RawCard(12), Some(RawCard(16))));
+        board.remove_cards((RawCard(14), Some(RawCard(22))));
+        assert_eq!(
+            board.remaining_idxs(),
+            (0..28)
+                .filter(|idx| ![21, 22, 23, 24].contains(idx))
+                .collect()
+        );
+    }
+
+    #[test]
+    fn test_is_dead() {
+        let board = get_base_board();
+        assert!(!board.is_dead());
+
+        // A 3 at the top of the pyramid, covered by two 10s, with the other two 3s in the stack
+        let mut cards: Vec<RawCard> = vec![
+            RawCard(2),
+            RawCard(9),
+            RawCard(22),
+            RawCard(1),
+            RawCard(10),
+            RawCard(12),
+        ];
+        cards.extend((60..82).map(RawCard));
+        let board = Board::new(
+            cards.clone(),
+            vec![RawCard(15), RawCard(28)],
+            vec![3, 4, 5],
+            false,
+        );
+        assert!(board.is_dead());
+
+        // Another 10 in the stack is enough to get the 3 out
+        let stack = vec![RawCard(15), RawCard(28), RawCard(35)];
+        let board = Board::new(cards, stack, vec![3, 4, 5], false);
+        assert!(!board.is_dead());
+    }
+
+    #[test]
+    fn test_is_dead_two_cards_one_partner() {
+        // Two 3s covering the only 10 on the board, with one 10 in the stack
+        let mut cards: Vec<RawCard> = vec![
+            RawCard(12),
+            RawCard(9),
+            RawCard(25),
+            RawCard(2),
+            RawCard(15),
+            RawCard(38),
+        ];
+        cards.extend((60..82).map(RawCard));
+        let board = Board::new(
+            cards.clone(),
+            vec![RawCard(22), RawCard(28)],
+            vec![3, 4, 5],
+            false,
+        );
+        assert!(board.is_dead());
+
+        let stack = vec![RawCard(22), RawCard(28), RawCard(35)];
+        let board = Board::new(cards, stack, vec![3, 4, 5], false);
+        assert!(!board.is_dead());
+    }
+
+    #[test]
+    fn test_lower_bound_moves() {
+        let mut board = get_base_board();
+
+        // 28 cards, with a single king on the board
+        assert_eq!(board.lower_bound_moves(), 1 + 14);
+
+        board.remove_cards((RawCard(12), None));
+        assert_eq!(board.lower_bound_moves(), 14);
+
+        board.clear_all = true;
+        // 27 on the board and 24 in the stack, of which 3 are kings
+        assert_eq!(board.lower_bound_moves(), 3 + 24);
+
+        // Two kings next to each other in the stack, which are both free once they're left of
+        // the stack pointer
+        board.stack_idx = 6;
+        assert_eq!(board.lower_bound_moves(), 2 + 24);
+        board.stack_idx = 7;
+        assert_eq!(board.lower_bound_moves(), 1 + 24);
+        board.stack_idx = 8;
+        assert_eq!(board.lower_bound_moves(), 3 + 24);
+    }
+
+    #[test]
+    fn test_lower_bound_moves_stack_reach() {
+        // A 3 at the top of the pyramid, covered by the only 10 on the board, and the other 10s
+        // deep in the stack
+        let mut cards: Vec<RawCard> = vec![RawCard(2), RawCard(9), RawCard(15)];
+        cards.extend((60..85).map(RawCard));
+        let mut stack: Vec<RawCard> = (90..100).map(RawCard).collect();
+        stack.push(RawCard(22));
+        let board = Board::new(cards, stack, vec![1, 2], false);
+
+        // 10 cards to get past, which takes at least 5 moves, and then the match itself
+        assert_eq!(board.lower_bound_moves(), 6);
+    }
+
     #[test]
     fn test_leaves() {
         let board = get_base_board();
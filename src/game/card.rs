@@ -1,17 +1,66 @@
 use crate::game::utils::card_from_raw;
+use anyhow::{bail, Error, Result};
 
-#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone, PartialOrd, Ord)]
+/// How a move matches cards, serialized as `"board"`, `"board_stack"` or `"stack"`
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone, PartialOrd, Ord, serde::Serialize)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
+#[serde(rename_all = "snake_case")]
 pub enum MatchType {
     Board,
     BoardStack,
     Stack,
 }
 
-#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, PartialOrd, Ord)]
+/// One of the 52 cards, numbered from 0 as the rank index plus 13 times the copy of the rank.
+/// Deals read with `parse_board` number the copies in the order they were typed, so they carry
+/// no suit. Only for deals decoded by a `DealCodec` that knows suits, such as `Standard` or
+/// `Unicode`, is the copy the suit, in the order spades, hearts, diamonds and clubs. Serialized
+/// as that number, and numbers past 51 fail to deserialize.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, PartialOrd, Ord, serde::Serialize)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize), serde(try_from = "u8"))]
+#[serde(into = "u8")]
 pub struct RawCard(pub u8);
-#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, PartialOrd, Ord)]
+/// The rank of a card, from 1 for an ace to 13 for a king. Serialized as that number, and other
+/// numbers fail to deserialize.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, PartialOrd, Ord, serde::Serialize)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize), serde(try_from = "u8"))]
+#[serde(into = "u8")]
 pub struct Card(pub u8);
 
+impl TryFrom<u8> for RawCard {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self> {
+        if value >= 52 {
+            bail!("Invalid card {}, cards go from 0 to 51", value);
+        }
+        Ok(RawCard(value))
+    }
+}
+
+impl From<RawCard> for u8 {
+    fn from(raw_card: RawCard) -> Self {
+        raw_card.0
+    }
+}
+
+impl TryFrom<u8> for Card {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self> {
+        if !(1..=13).contains(&value) {
+            bail!("Invalid rank {}, ranks go from 1 to 13", value);
+        }
+        Ok(Card(value))
+    }
+}
+
+impl From<Card> for u8 {
+    fn from(card: Card) -> Self {
+        card.0
+    }
+}
+
 impl From<RawCard> for Card {
     fn from(raw_card: RawCard) -> Self {
         Card(card_from_raw(raw_card.0))
@@ -34,6 +83,28 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_card_ranges() {
+        assert_eq!(RawCard::try_from(51).unwrap(), RawCard(51));
+        assert!(RawCard::try_from(52).is_err());
+        assert_eq!(Card::try_from(13).unwrap(), Card(13));
+        assert!(Card::try_from(0).is_err());
+        assert!(Card::try_from(14).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_card_serde_ranges() {
+        assert_eq!(serde_json::to_string(&RawCard(51)).unwrap(), "51");
+        assert_eq!(serde_json::from_str::<RawCard>("51").unwrap(), RawCard(51));
+        assert!(serde_json::from_str::<RawCard>("52").is_err());
+
+        assert_eq!(serde_json::to_string(&Card(13)).unwrap(), "13");
+        assert_eq!(serde_json::from_str::<Card>("1").unwrap(), Card(1));
+        assert!(serde_json::from_str::<Card>("0").is_err());
+        assert!(serde_json::from_str::<Card>("14").is_err());
+    }
+
     #[test]
     fn test_raw_card_order() {
         let mut cards = vec![
@@ -0,0 +1,270 @@
+use crate::game::card::{Card, RawCard};
+use crate::game::utils::parse_board;
+use anyhow::{bail, Result};
+use std::collections::HashSet;
+
+/// Suits in the order of the copies of a rank, so the first copy `parse_board` gives is a spade
+const SUITS: [char; 4] = ['S', 'H', 'D', 'C'];
+const SUIT_SYMBOLS: [char; 4] = ['♠', '♥', '♦', '♣'];
+const WHITE_SUIT_SYMBOLS: [char; 4] = ['♤', '♡', '♢', '♧'];
+
+/// A notation to read and write deals in
+pub trait DealCodec {
+    /// Name of the notation
+    fn name(&self) -> &'static str;
+
+    /// Parse the board and stack into raw cards
+    fn decode(&self, board: &str, stack: &str) -> Result<(Vec<RawCard>, Vec<RawCard>)>;
+
+    /// Write the board and stack in this notation
+    fn encode(&self, board_cards: &[RawCard], stack_cards: &[RawCard]) -> (String, String);
+}
+
+/// One character for each rank, as `parse_board` reads, for example `jj6j88a95k`
+///
+/// Suits aren't written, so they're lost going through this notation
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Compact;
+
+impl DealCodec for Compact {
+    fn name(&self) -> &'static str {
+        "compact"
+    }
+
+    fn decode(&self, board: &str, stack: &str) -> Result<(Vec<RawCard>, Vec<RawCard>)> {
+        parse_board(board.to_string(), stack.to_string())
+    }
+
+    fn encode(&self, board_cards: &[RawCard], stack_cards: &[RawCard]) -> (String, String) {
+        let encode = |cards: &[RawCard]| -> String {
+            cards
+                .iter()
+                .map(|&card| match Card::from(card).0 {
+                    1 => 'a',
+                    10 => '0',
+                    11 => 'j',
+                    12 => 'q',
+                    13 => 'k',
+                    rank => char::from_digit(rank as u32, 10).unwrap(),
+                })
+                .collect()
+        };
+        (encode(board_cards), encode(stack_cards))
+    }
+}
+
+/// A rank and a suit for each card, separated by whitespace, for example `AS 2H TD`
+///
+/// 10 can be given as T, 10 or 0, and suits as letters or symbols such as `10♣`. With
+/// `suit_symbols` set, cards are written with symbols and 10 as 10.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Standard {
+    pub suit_symbols: bool,
+}
+
+impl DealCodec for Standard {
+    fn name(&self) -> &'static str {
+        if self.suit_symbols {
+            "symbols"
+        } else {
+            "standard"
+        }
+    }
+
+    fn decode(&self, board: &str, stack: &str) -> Result<(Vec<RawCard>, Vec<RawCard>)> {
+        let decode = |text: &str| -> Result<Vec<RawCard>> {
+            text.split_whitespace().map(decode_card).collect()
+        };
+        unique_cards(decode(board)?, decode(stack)?)
+    }
+
+    fn encode(&self, board_cards: &[RawCard], stack_cards: &[RawCard]) -> (String, String) {
+        let encode = |cards: &[RawCard]| -> String {
+            cards
+                .iter()
+                .map(|&card| encode_card(card, self.suit_symbols))
+                .collect::<Vec<String>>()
+                .join(" ")
+        };
+        (encode(board_cards), encode(stack_cards))
+    }
+}
+
+/// The playing card characters of Unicode, one for each card, for example `🂡🂲`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Unicode;
+
+impl DealCodec for Unicode {
+    fn name(&self) -> &'static str {
+        "unicode"
+    }
+
+    fn decode(&self, board: &str, stack: &str) -> Result<(Vec<RawCard>, Vec<RawCard>)> {
+        let decode = |text: &str| -> Result<Vec<RawCard>> {
+            text.chars()
+                .filter(|char| !char.is_whitespace())
+                .map(|char| {
+                    let code = char as u32;
+                    let (suit, rank) = (code.wrapping_sub(0x1F0A0) / 16, code % 16);
+                    let rank = match rank {
+                        1..=11 => rank,
+                        13 | 14 => rank - 1,
+                        _ => 0,
+                    };
+                    if suit >= 4 || rank == 0 {
+                        bail!("Unknown card ({}) - Knights and jokers aren't used", char);
+                    }
+                    Ok(RawCard((rank - 1 + suit * 13) as u8))
+                })
+                .collect()
+        };
+        unique_cards(decode(board)?, decode(stack)?)
+    }
+
+    fn encode(&self, board_cards: &[RawCard], stack_cards: &[RawCard]) -> (String, String) {
+        let encode = |cards: &[RawCard]| -> String {
+            cards
+                .iter()
+                .map(|&card| {
+                    let (suit, rank) = (card.0 as u32 / 13, Card::from(card).0 as u32);
+                    // The knight sits between the jack and the queen
+                    let rank = if rank > 11 { rank + 1 } else { rank };
+                    char::from_u32(0x1F0A0 + suit * 16 + rank).unwrap()
+                })
+                .collect()
+        };
+        (encode(board_cards), encode(stack_cards))
+    }
+}
+
+/// Parse a card such as `AS`, `TD` or `10♣`
+pub(crate) fn decode_card(token: &str) -> Result<RawCard> {
+    let mut chars: Vec<char> = token.chars().collect();
+    let suit = chars.pop().and_then(|suit| {
+        let suit = suit.to_ascii_uppercase();
+        [SUITS, SUIT_SYMBOLS, WHITE_SUIT_SYMBOLS]
+            .iter()
+            .find_map(|suits| suits.iter().position(|&other| other == suit))
+    });
+    let rank: String = chars.iter().collect::<String>().to_uppercase();
+    let rank = match rank.as_str() {
+        "A" | "1" => 1,
+        "T" | "10" | "0" => 10,
+        "J" => 11,
+        "Q" => 12,
+        "K" => 13,
+        _ => rank
+            .parse::<u8>()
+            .ok()
+            .filter(|rank| (2..=9).contains(rank))
+            .unwrap_or(0),
+    };
+
+    match suit {
+        Some(suit) if rank > 0 => Ok(RawCard(rank - 1 + suit as u8 * 13)),
+        _ => bail!(
+            "Unknown card ({}) - Use a rank of A, 2-9, T, J, Q or K followed by a suit of S, H, D or C",
+            token
+        ),
+    }
+}
+
+/// Write a card such as `AS` or `TD`, or with `suit_symbols` such as `10♣`
+pub(crate) fn encode_card(card: RawCard, suit_symbols: bool) -> String {
+    let rank = match Card::from(card).0 {
+        1 => "A".to_string(),
+        10 if suit_symbols => "10".to_string(),
+        10 => "T".to_string(),
+        11 => "J".to_string(),
+        12 => "Q".to_string(),
+        13 => "K".to_string(),
+        rank => rank.to_string(),
+    };
+    let suits = if suit_symbols { SUIT_SYMBOLS } else { SUITS };
+    format!("{}{}", rank, suits[card.0 as usize / 13])
+}
+
+/// Fail if any card is given twice, as with suits every card is unique
+pub(crate) fn unique_cards(
+    board_cards: Vec<RawCard>,
+    stack_cards: Vec<RawCard>,
+) -> Result<(Vec<RawCard>, Vec<RawCard>)> {
+    let mut seen: HashSet<RawCard> = HashSet::new();
+    for &card in board_cards.iter().chain(stack_cards.iter()) {
+        if !seen.insert(card) {
+            bail!(
+                "The {} is in the deal more than once",
+                encode_card(card, false)
+            );
+        }
+    }
+    Ok((board_cards, stack_cards))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const BOARD: &str = "jj6j88a95k3ka02j4q32k0767qk7";
+    const STACK: &str = "68480a55q69a2339527q4490";
+
+    #[test]
+    fn test_codecs_round_trip() {
+        let (board_cards, stack_cards) = Compact.decode(BOARD, STACK).unwrap();
+
+        let codecs: [Box<dyn DealCodec>; 4] = [
+            Box::new(Compact),
+            Box::new(Standard::default()),
+            Box::new(Standard { suit_symbols: true }),
+            Box::new(Unicode),
+        ];
+        for codec in codecs {
+            let (board, stack) = codec.encode(&board_cards, &stack_cards);
+            let decoded = codec.decode(&board, &stack).unwrap();
+            assert_eq!(
+                decoded,
+                (board_cards.clone(), stack_cards.clone()),
+                "{}",
+                codec.name()
+            );
+        }
+        assert_eq!(
+            Compact.encode(&board_cards, &stack_cards),
+            (BOARD.to_string(), STACK.to_string())
+        );
+    }
+
+    #[test]
+    fn test_standard() {
+        let codec = Standard::default();
+        let (board, stack) = codec.decode("AS 2h TD 10♣", "0h k♥ 7♧").unwrap();
+
+        assert_eq!(
+            board,
+            vec![RawCard(0), RawCard(14), RawCard(35), RawCard(48)]
+        );
+        assert_eq!(stack, vec![RawCard(22), RawCard(25), RawCard(45)]);
+        assert_eq!(
+            Standard { suit_symbols: true }.encode(&board, &[]).0,
+            "A♠ 2♥ 10♦ 10♣"
+        );
+
+        let error = codec.decode("AS 1X", "").unwrap_err().to_string();
+        assert!(error.starts_with("Unknown card (1X)"));
+        let error = codec.decode("AS", "1s").unwrap_err().to_string();
+        assert_eq!(error, "The AS is in the deal more than once");
+    }
+
+    #[test]
+    fn test_unicode() {
+        let (board, stack) = Unicode.decode("🂡 🂲", "🃎🃝").unwrap();
+
+        assert_eq!(board, vec![RawCard(0), RawCard(14)]);
+        assert_eq!(stack, vec![RawCard(38), RawCard(50)]);
+        assert_eq!(
+            Unicode.encode(&board, &stack),
+            ("🂡🂲".to_string(), "🃎🃝".to_string())
+        );
+        assert!(Unicode.decode("🂬", "").is_err());
+    }
+}
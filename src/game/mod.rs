@@ -1,5 +1,8 @@
 pub(crate) mod blocks;
 pub(crate) mod board;
 pub(crate) mod card;
+pub(crate) mod codec;
 pub(crate) mod r#move;
+pub(crate) mod notation;
 pub(crate) mod utils;
+pub(crate) mod verify;
@@ -1,32 +1,60 @@
 use crate::game::card::{MatchType, RawCard};
 use std::cmp::Ordering;
 
-pub type Move = (MatchType, i32, (RawCard, Option<RawCard>));
+/// A move that can be played on a board
+///
+/// Serialized as `match_type`, `draws`, `left` and `right`, with the cards as `RawCard` numbers
+/// and `right` as `null` for kings. Cards past 51 fail to deserialize.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, PartialOrd, Ord, serde::Serialize)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
+pub struct Move {
+    pub match_type: MatchType,
+    /// Cards drawn from the stack first, or -1 when the card left of the stack pointer is played
+    /// on its own
+    pub draws: i32,
+    /// The card removed, which is the board card when matching the board with the stack
+    pub left: RawCard,
+    /// The card it's matched with, if it isn't a king
+    pub right: Option<RawCard>,
+}
+
+impl Move {
+    pub fn new(match_type: MatchType, draws: i32, left: RawCard, right: Option<RawCard>) -> Move {
+        Move {
+            match_type,
+            draws,
+            left,
+            right,
+        }
+    }
+
+    /// The cards removed by the move
+    pub fn cards(&self) -> (RawCard, Option<RawCard>) {
+        (self.left, self.right)
+    }
+}
 
-pub fn move_sort(
-    (a_move_type, a_draws, (a_left_card, a_right_card)): &Move,
-    (b_move_type, b_draws, (b_left_card, b_right_card)): &Move,
-) -> Ordering {
+pub fn move_sort(a: &Move, b: &Move) -> Ordering {
     // 1. Number of draws first
-    if a_draws != b_draws {
-        return a_draws.cmp(b_draws);
+    if a.draws != b.draws {
+        return a.draws.cmp(&b.draws);
     }
     // 2. Then move type
-    if a_move_type != b_move_type {
-        return a_move_type.cmp(b_move_type);
+    if a.match_type != b.match_type {
+        return a.match_type.cmp(&b.match_type);
     }
 
     // 3. The left card, if not the same
-    if a_left_card != b_left_card {
-        return a_left_card.cmp(b_left_card);
+    if a.left != b.left {
+        return a.left.cmp(&b.left);
     }
     // 4. less/greated depending on right card being None
-    if a_right_card.is_none() && b_right_card.is_some() {
+    if a.right.is_none() && b.right.is_some() {
         return Ordering::Less;
     }
-    if a_right_card.is_some() && b_right_card.is_none() {
+    if a.right.is_some() && b.right.is_none() {
         return Ordering::Greater;
     }
     // 5. The right card if both are Some
-    a_right_card.cmp(b_right_card)
+    a.right.cmp(&b.right)
 }
@@ -0,0 +1,354 @@
+use crate::game::board::Board;
+use crate::game::card::{Card, RawCard};
+use crate::game::r#move::Move;
+use anyhow::{bail, Context, Result};
+use std::fmt;
+use std::str::FromStr;
+
+/// Where a card is played from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Location {
+    /// A card of the pyramid, both counted from 1, written as `R3C2`
+    Board { row: u8, col: u8 },
+    /// The card at the stack pointer after drawing, written as `S`
+    Stack,
+    /// The card left of the stack pointer, which is the last card drawn, written as `W`
+    Waste,
+}
+
+impl Location {
+    /// Location of a card on `board`, after drawing `draws` cards of the stack
+    fn of(board: &Board, card: RawCard, draws: i32) -> Location {
+        if let Some(idx) = board.board_cards.iter().position(|&c| c == card) {
+            let row = (1..=7).find(|row| idx < row * (row + 1) / 2).unwrap();
+            return Location::Board {
+                row: row as u8,
+                col: (idx - row * (row - 1) / 2 + 1) as u8,
+            };
+        }
+
+        let mut stack_idx = board.stack_idx + draws.max(0);
+        if stack_idx > board.stack.len() as i32 {
+            stack_idx -= board.stack.len() as i32 + 1;
+        }
+        if board.stack.get(stack_idx as usize) == Some(&card) {
+            Location::Stack
+        } else {
+            Location::Waste
+        }
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Location::Board { row, col } => write!(f, "R{}C{}", row, col),
+            Location::Stack => write!(f, "S"),
+            Location::Waste => write!(f, "W"),
+        }
+    }
+}
+
+impl FromStr for Location {
+    type Err = anyhow::Error;
+
+    fn from_str(text: &str) -> Result<Location> {
+        let upper = text.trim().to_uppercase();
+        match upper.as_str() {
+            "S" => return Ok(Location::Stack),
+            "W" => return Ok(Location::Waste),
+            _ => {}
+        }
+
+        let board = upper
+            .strip_prefix('R')
+            .and_then(|rest| rest.split_once('C'))
+            .and_then(|(row, col)| Some((row.parse::<u8>().ok()?, col.parse::<u8>().ok()?)))
+            .filter(|&(row, col)| (1..=7).contains(&row) && (1..=row).contains(&col));
+        match board {
+            Some((row, col)) => Ok(Location::Board { row, col }),
+            None => bail!(
+                "Unknown location ({}) - Use R<row>C<card> for the board, S for the stack or W for the card left of it",
+                text.trim()
+            ),
+        }
+    }
+}
+
+/// A move written independently of the order `Board::get_moves` gives moves in
+///
+/// Written as the cards to draw, if any, then `M` and the two cards to match, or `K` and the king
+/// to remove. For example `D3 M R3C2+S` draws 3 cards and then matches the 2nd card of the 3rd
+/// row with the card on the stack, and `K R7C1` removes the king at the start of the bottom row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MoveNotation {
+    pub draws: u32,
+    pub cards: (Location, Option<Location>),
+}
+
+impl MoveNotation {
+    /// How `r#move` is written when played on `board`
+    pub fn of(
+        board: &Board,
+        Move {
+            draws, left, right, ..
+        }: Move,
+    ) -> MoveNotation {
+        MoveNotation {
+            draws: draws.max(0) as u32,
+            cards: (
+                Location::of(board, left, draws),
+                right.map(|right| Location::of(board, right, draws)),
+            ),
+        }
+    }
+
+    /// Index of this move in `board.get_moves()`, failing if it isn't one of them
+    pub fn find(&self, board: &Board) -> Result<usize> {
+        let swapped = MoveNotation {
+            draws: self.draws,
+            cards: match self.cards {
+                (left, Some(right)) => (right, Some(left)),
+                cards => cards,
+            },
+        };
+        match board.get_moves().into_iter().position(|r#move| {
+            let notation = MoveNotation::of(board, r#move);
+            notation == *self || notation == swapped
+        }) {
+            Some(idx) => Ok(idx),
+            None => bail!("{} isn't one of the moves that can be played", self),
+        }
+    }
+}
+
+impl fmt::Display for MoveNotation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.draws > 0 {
+            write!(f, "D{} ", self.draws)?;
+        }
+        match self.cards {
+            (left, Some(right)) => write!(f, "M {}+{}", left, right),
+            (card, None) => write!(f, "K {}", card),
+        }
+    }
+}
+
+impl FromStr for MoveNotation {
+    type Err = anyhow::Error;
+
+    fn from_str(text: &str) -> Result<MoveNotation> {
+        let mut tokens = text.split_whitespace().peekable();
+
+        let draws = match tokens.peek() {
+            Some(token) if token.to_uppercase().starts_with('D') => {
+                let draws = token[1..]
+                    .parse::<u32>()
+                    .with_context(|| format!("Unknown draw ({}) - Use D and a number", token))?;
+                tokens.next();
+                draws
+            }
+            _ => 0,
+        };
+
+        let kind = tokens.next().map(|token| token.to_uppercase());
+        let cards: String = tokens.collect();
+        let cards = match kind.as_deref() {
+            Some("M") => match cards.split_once('+') {
+                Some((left, right)) => (left.parse()?, Some(right.parse()?)),
+                None => bail!("A match needs two cards joined by +, such as M R7C1+S"),
+            },
+            Some("K") if !cards.is_empty() => (cards.parse()?, None),
+            Some("K") => bail!("A king needs a location, such as K R7C1"),
+            _ => bail!(
+                "Unknown move ({}) - Use M for a match or K for a king",
+                text.trim()
+            ),
+        };
+
+        Ok(MoveNotation { draws, cards })
+    }
+}
+
+/// Write the moves of a solution one on each line, starting with a comment holding the position
+///
+/// `moves_played` are 1-based indexes into `get_moves`, as in `Solution`. A line that doesn't win
+/// the game ends with an `# Unsolved` comment saying how many cards are left.
+pub fn write_solution(board: &Board, moves_played: &[usize]) -> String {
+    let mut board = board.clone();
+    let mut text = format!("# Position: {}\n", board.to_canonical());
+    for &move_num in moves_played {
+        let r#move = board.get_moves()[move_num - 1];
+        text.push_str(&format!("{}\n", MoveNotation::of(&board, r#move)));
+        board.play_move(r#move);
+    }
+    text.push_str(&format!("# Moves made: {}\n", board.moves));
+    if !board.completed {
+        text.push_str(&format!("# Unsolved: {} cards left\n", board.cards_left()));
+    }
+    text
+}
+
+/// Read the moves written by `write_solution`, skipping empty lines and anything after a #
+pub fn read_solution(text: &str) -> Result<Vec<MoveNotation>> {
+    text.lines()
+        .enumerate()
+        .map(|(idx, line)| (idx, line.split('#').next().unwrap().trim()))
+        .filter(|(_, line)| !line.is_empty())
+        .map(|(idx, line)| {
+            line.parse()
+                .with_context(|| format!("Line {} isn't a valid move", idx + 1))
+        })
+        .collect()
+}
+
+/// The board a solution written by `write_solution` starts from, given the deal it's for
+///
+/// The position in the `# Position:` comment is used when there is one, which can be a game in
+/// progress on `deal` and clears the stack as well when it was written that way. Its cards only
+/// have to match `deal` by rank, as the suits of a deal can be lost going through a notation.
+/// Fails if the position can't have been reached from `deal`.
+pub fn solution_board(deal: &Board, text: &str) -> Result<Board> {
+    let Some(canonical) = text
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("# Position:"))
+    else {
+        return Ok(deal.clone());
+    };
+    let board = Board::from_canonical(canonical)
+        .context("Couldn't read the position at the start of the solution")?;
+
+    let deal_idxs = deal.remaining_idxs();
+    if board.remaining_idxs().iter().any(|&idx| {
+        !deal_idxs.contains(&idx)
+            || Card::from(board.board_cards[idx]) != Card::from(deal.board_cards[idx])
+    }) {
+        bail!("The solution was saved for another board than the one of the deal");
+    }
+    let mut deal_stack = deal.stack.iter().map(|&card| Card::from(card));
+    if !board
+        .stack
+        .iter()
+        .all(|&card| deal_stack.any(|other| other == Card::from(card)))
+    {
+        bail!("The solution was saved for another stack than the one of the deal");
+    }
+    if deal.clears_all() && !board.clears_all() {
+        bail!("The solution was saved for a game that doesn't clear the stack");
+    }
+    Ok(board)
+}
+
+/// Play the moves on `board`, returning them as 1-based indexes into `get_moves` as in `Solution`
+pub fn replay_solution(board: &Board, notations: &[MoveNotation]) -> Result<Vec<usize>> {
+    let mut board = board.clone();
+    let mut moves_played = vec![];
+    for (idx, notation) in notations.iter().enumerate() {
+        let move_idx = notation
+            .find(&board)
+            .with_context(|| format!("Move {} ({}) can't be played", idx + 1, notation))?;
+        board.play_move(board.get_moves()[move_idx]);
+        moves_played.push(move_idx + 1);
+    }
+    Ok(moves_played)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::{readme_board, solve};
+
+    #[test]
+    fn test_notation_round_trip() {
+        for text in ["D3 M R3C2+S", "M W+S", "K R7C1", "D12 K S", "M R7C2+R7C6"] {
+            let notation: MoveNotation = text.parse().unwrap();
+            assert_eq!(notation.to_string(), text);
+        }
+        assert_eq!(
+            "d3 m r3c2 + s".parse::<MoveNotation>().unwrap(),
+            MoveNotation {
+                draws: 3,
+                cards: (Location::Board { row: 3, col: 2 }, Some(Location::Stack)),
+            }
+        );
+
+        for text in ["", "D3", "Dx M S+W", "M R3C2", "M R3C4+S", "K", "X R1C1"] {
+            assert!(text.parse::<MoveNotation>().is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_notation_of_moves() {
+        let board = readme_board();
+
+        for (idx, r#move) in board.get_moves().into_iter().enumerate() {
+            let notation = MoveNotation::of(&board, r#move);
+            assert_eq!(notation.find(&board).unwrap(), idx, "{}", notation);
+        }
+
+        // The king at the end of the bottom row goes first
+        let notation = MoveNotation::of(&board, board.get_moves()[0]);
+        assert_eq!(notation.to_string(), "K R7C6");
+        assert!("K R7C1"
+            .parse::<MoveNotation>()
+            .unwrap()
+            .find(&board)
+            .is_err());
+    }
+
+    #[test]
+    fn test_solution_board() {
+        let deal = readme_board();
+        assert_eq!(
+            solution_board(&deal, "K R7C6\n").unwrap().to_canonical(),
+            deal.to_canonical()
+        );
+
+        let mut played = deal.clone();
+        played.play_move(played.get_moves()[0]);
+        let text = write_solution(&played, &[1]);
+        let board = solution_board(&deal, &text).unwrap();
+        assert_eq!(board.to_canonical(), played.to_canonical());
+
+        // The suits don't have to match, only the ranks
+        let text = text.replacen("JS JH", "JH JS", 1);
+        assert!(solution_board(&deal, &text).is_ok());
+        let text = text.replacen("JH JS", "JH KC", 1);
+        let error = solution_board(&deal, &text).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "The solution was saved for another board than the one of the deal"
+        );
+
+        let clear_all = Board::new(
+            deal.board_cards.to_vec(),
+            deal.stack.clone(),
+            (21..28).collect(),
+            true,
+        );
+        assert!(solution_board(&clear_all, &write_solution(&deal, &[])).is_err());
+        assert!(solution_board(&deal, &write_solution(&clear_all, &[])).is_ok());
+    }
+
+    #[test]
+    fn test_replay_solution() {
+        let board = readme_board();
+        let solution = solve(&board);
+
+        let text = write_solution(&board, &solution.moves_played);
+        assert!(text.starts_with("# Position: "));
+        assert!(!text.contains("# Unsolved"));
+        assert!(write_solution(&board, &solution.moves_played[..5]).ends_with("cards left\n"));
+        let notations = read_solution(&text).unwrap();
+        assert_eq!(notations.len(), solution.moves_played.len());
+        assert_eq!(
+            replay_solution(&board, &notations).unwrap(),
+            solution.moves_played
+        );
+
+        let error = read_solution("K R7C6\n\nM R9C1+S\n").unwrap_err();
+        assert_eq!(error.to_string(), "Line 3 isn't a valid move");
+        let error = replay_solution(&board, &notations[1..]).unwrap_err();
+        assert!(error.to_string().starts_with("Move 1 ("));
+    }
+}
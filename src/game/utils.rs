@@ -1,3 +1,4 @@
+use crate::game::blocks::card_blocked_by;
 use crate::game::board::Board;
 use crate::game::card::{Card, RawCard};
 use crate::game::r#move::Move;
@@ -23,7 +24,6 @@ pub fn parse_verbosity(verbosity: u8) -> Verbosity {
         _ => Verbosity::VeryHigh,
     }
 }
-    
 
 /// A raw value will be from 0 to 51
 /// Aces will be 0, 13, 26 and 39 for example
@@ -44,6 +44,22 @@ pub fn match_card(card: Card) -> Card {
     }
 }
 
+/// Parse a single card character into its value, from 0 (Ace) to 12 (King)
+pub(crate) fn card_value(char: char) -> Result<u8> {
+    Ok(match char {
+        'a' | 'A' => 1,
+        'j' | 'J' => 11,
+        'q' | 'Q' | 'd' | 'D' => 12, // I keep typing queen as d
+        'k' | 'K' => 13,
+        '0' | 't' | 'T' => 10,
+        '1'..='9' => char.to_digit(10).unwrap() as u8,
+        _ => bail!(
+            "Unknown value ({}) - Use a for Ace, j for Jack, q for Queen, k for King and 0 or t for 10",
+            char
+        ),
+    } - 1)
+}
+
 /// Parse cards and stack strings into vectors of raw cards
 ///
 /// For example the string 76jkj would parse into:
@@ -61,15 +77,7 @@ pub fn parse_board(cards_str: String, stack_str: String) -> Result<(Vec<RawCard>
 
     // Go through the cards first
     for char in cards_str.chars() {
-        let val = match char {
-            'a' | 'A' => 1,
-            'j' | 'J' => 11,
-            'q' | 'Q' | 'd' | 'D' => 12, // I keep typing queen as d
-            'k' | 'K' => 13,
-            '0' => 10,
-            '1'..='9' => char.to_digit(10).unwrap() as u8,
-            _ => bail!("Unknown value ({}) - Use a for Ace, j for Jack, q for Queen, k for King and 0 for 10", char),
-        } - 1;
+        let val = card_value(char)?;
         let count = counts.entry(val).or_insert(0);
         cards.push(RawCard(val + *count * 13));
         *count += 1;
@@ -77,15 +85,7 @@ pub fn parse_board(cards_str: String, stack_str: String) -> Result<(Vec<RawCard>
 
     // Then the stack
     for char in stack_str.chars() {
-        let val = match char {
-            'a' | 'A' => 1,
-            'j' | 'J' => 11,
-            'q' | 'Q' | 'd' | 'D' => 12,
-            'k' | 'K' => 13,
-            '0' => 10,
-            '1'..='9' => char.to_digit(10).unwrap() as u8,
-            _ => bail!("Unknown value ({}) - Use a for Ace, j for Jack, q for Queen, k for King and 0 for 10", char),
-        } - 1;
+        let val = card_value(char)?;
         let count = counts.entry(val).or_insert(0);
         stack.push(RawCard(val + *count * 13));
         *count += 1;
@@ -94,9 +94,243 @@ pub fn parse_board(cards_str: String, stack_str: String) -> Result<(Vec<RawCard>
     Ok((cards, stack))
 }
 
+/// Parse cards and a stack that can have unknown cards, marked with ?, into raw cards
+///
+/// Known cards are numbered the same way as with `parse_board`, skipping over the unknown ones, so
+/// that they keep the same raw card no matter what the unknown cards turn out to be.
+pub fn parse_partial_board(
+    cards_str: String,
+    stack_str: String,
+) -> Result<(Vec<RawCard>, Vec<Option<RawCard>>)> {
+    let (cards, known_stack) = parse_board(cards_str, stack_str.replace('?', ""))?;
+
+    let mut known_stack = known_stack.into_iter();
+    let stack = stack_str
+        .chars()
+        .map(|char| {
+            if char == '?' {
+                None
+            } else {
+                known_stack.next()
+            }
+        })
+        .collect();
+
+    Ok((cards, stack))
+}
+
+/// Parse the cards of a game in progress, where cards removed from the board are marked with .
+///
+/// Cards are numbered the same way as with `parse_board`, skipping over the removed ones. The stack
+/// only has the cards still left in it.
+pub fn parse_position(
+    cards_str: String,
+    stack_str: String,
+) -> Result<(Vec<Option<RawCard>>, Vec<RawCard>)> {
+    let (known_cards, stack) = parse_board(cards_str.replace('.', ""), stack_str)?;
+
+    let mut known_cards = known_cards.into_iter();
+    let cards = cards_str
+        .chars()
+        .map(|char| {
+            if char == '.' {
+                None
+            } else {
+                known_cards.next()
+            }
+        })
+        .collect();
+
+    Ok((cards, stack))
+}
+
+/// Check the cards of a game in progress, as parsed by `parse_position`
+///
+/// Every card left can only be present once, and a card can only have been removed from the board
+/// once the cards covering it were. Every problem is reported at once, unless the board doesn't
+/// have 28 cards, as the others can't be told apart then.
+pub fn validate_position(
+    board_cards: &[Option<RawCard>],
+    stack_cards: &[RawCard],
+    stack_idx: usize,
+) -> Result<()> {
+    check_position(board_cards, stack_cards, stack_idx, false)
+}
+
+/// `validate_position`, but also accepting a position with the top of the pyramid removed, as a
+/// won game leaves the board
+pub(crate) fn validate_reached_position(
+    board_cards: &[Option<RawCard>],
+    stack_cards: &[RawCard],
+    stack_idx: usize,
+) -> Result<()> {
+    check_position(board_cards, stack_cards, stack_idx, true)
+}
+
+fn check_position(
+    board_cards: &[Option<RawCard>],
+    stack_cards: &[RawCard],
+    stack_idx: usize,
+    allow_won: bool,
+) -> Result<()> {
+    if board_cards.len() != 28 {
+        bail!(
+            "The board has {} cards, but it needs 28 with the removed ones marked with .",
+            board_cards.len()
+        )
+    }
+    let mut problems: Vec<String> = vec![];
+
+    let mut card_counts: Vec<u8> = vec![0; 13];
+    for card in board_cards.iter().flatten().chain(stack_cards.iter()) {
+        let card_value = Card::from(*card).0 - 1;
+        card_counts[card_value as usize] += 1;
+    }
+    for (idx, count) in card_counts.iter().enumerate() {
+        if *count > 4 {
+            problems.push(format!(
+                "Card {} is present {} times, but every card can only be present 4 times across the board and stack",
+                pretty_print_card(RawCard(idx as u8), true),
+                count
+            ));
+        }
+    }
+
+    if stack_cards.len() > 24 {
+        problems.push(format!(
+            "The stack has {} cards left, but it can only hold 24",
+            stack_cards.len()
+        ));
+    }
+
+    if board_cards[0].is_none() && !allow_won {
+        problems.push(
+            "The top of the pyramid has been removed, so the game is already won".to_string(),
+        );
+    }
+    for (idx, card) in board_cards.iter().enumerate() {
+        let covered_by = card_blocked_by(idx)
+            .into_iter()
+            .find(|&other| board_cards[other].is_some());
+        if let (None, Some(other)) = (card, covered_by) {
+            problems.push(format!(
+                "Card {} of the board has been removed, but card {} covering it is still there",
+                idx + 1,
+                other + 1
+            ));
+        }
+    }
+
+    if stack_idx > stack_cards.len() {
+        problems.push(format!(
+            "The stack position is {}, but there are only {} cards left in the stack",
+            stack_idx,
+            stack_cards.len()
+        ));
+    }
+
+    if !problems.is_empty() {
+        bail!("The position isn't valid:\n    {}", problems.join("\n    "))
+    }
+
+    Ok(())
+}
+
+/// Parse a deal laid out as a pyramid, into the board and stack strings that `parse_board` takes
+///
+/// The first 7 lines with cards are the rows of the pyramid, and every line after that is the
+/// stack, which can start with a label such as `Stack:`. Cards can be separated by whitespace, 10
+/// can be given as 10, T or 0, and anything after a # is a comment. Removed cards (.) and unknown
+/// ones (?) are kept as they are.
+///
+/// For example:
+///           J
+///          J 6
+///         J 8 8
+///        A 9 5 K
+///       3 K A 10 2
+///      J 4 Q 3 2 K
+///     10 7 6 7 Q K 7
+///     Stack: 6 8 4 8 10 A 5 5 Q 6 9 A 2 3 3 9 5 2 7 Q 4 4 9 10
+pub fn parse_deal_file(text: &str) -> Result<(String, String)> {
+    let mut rows: Vec<String> = vec![];
+    let mut stack = String::new();
+
+    for (line_idx, line) in text.lines().enumerate() {
+        let line_num = line_idx + 1;
+        let line = line.split('#').next().unwrap();
+        let mut cards = String::new();
+
+        // Skip over a label in front of the stack
+        let mut start = 0;
+        if rows.len() == 7 {
+            if let Some(colon) = line.find(':') {
+                if line[..colon].trim().chars().all(char::is_alphabetic) {
+                    start = colon + 1;
+                }
+            }
+        }
+
+        let mut chars = line
+            .char_indices()
+            .skip_while(|&(idx, _)| idx < start)
+            .peekable();
+        while let Some((col, char)) = chars.next() {
+            let card = match char {
+                _ if char.is_whitespace() => continue,
+                '1' if chars.peek().is_some_and(|&(_, next)| next == '0') => {
+                    chars.next();
+                    '0'
+                }
+                't' | 'T' => '0',
+                '.' | '?' => char,
+                _ => match card_value(char) {
+                    Ok(_) => char,
+                    Err(err) => bail!(
+                        "Line {}, column {}: {}",
+                        line_num,
+                        line[..col].chars().count() + 1,
+                        err
+                    ),
+                },
+            };
+            cards.push(card);
+        }
+
+        if cards.is_empty() {
+            continue;
+        }
+        if rows.len() < 7 {
+            let row = rows.len() + 1;
+            let count = cards.chars().count();
+            if count != row {
+                bail!(
+                    "Line {}: row {} of the pyramid should have {} cards, but has {}",
+                    line_num,
+                    row,
+                    row,
+                    count
+                );
+            }
+            rows.push(cards);
+        } else {
+            stack.push_str(&cards);
+        }
+    }
+
+    if rows.len() < 7 {
+        bail!(
+            "The pyramid should have 7 rows, but only {} were given",
+            rows.len()
+        );
+    }
+    Ok((rows.concat(), stack))
+}
+
 pub fn pretty_print_board(board: &Board) {
     // The cards are stored in a single array. Print them in a pyramid shape of 7 rows, where the
     // top row is one card, followed by two cards, then three, etc.
+    let remaining = board.remaining_idxs();
     let mut idx = 0;
     for row in 0..7 {
         let mut line = String::new();
@@ -105,7 +339,9 @@ pub fn pretty_print_board(board: &Board) {
         }
         print!("{}", line);
         for _ in 0..(row + 1) {
-            if board.leaf_idxs.contains(&idx) {
+            if !remaining.contains(&idx) {
+                print!(". ");
+            } else if board.leaf_idxs.contains(&idx) {
                 print!(
                     "{} ",
                     pretty_print_card(board.board_cards[idx], false).purple()
@@ -165,8 +401,13 @@ pub fn pretty_print_card(card: RawCard, full_width: bool) -> ColoredString {
 
 pub fn pretty_print_move(
     board: &Board,
-    idx: u8,
-    (_, draws, (left_card, right_card)): Move,
+    idx: i32,
+    Move {
+        draws,
+        left: left_card,
+        right: right_card,
+        ..
+    }: Move,
     split_draws: bool,
 ) {
     if split_draws {
@@ -189,7 +430,7 @@ pub fn pretty_print_move(
             ),
         };
 
-        println!("[{}] {}", idx as i32 + draws, cards_str);
+        println!("[{}] {}", idx + draws, cards_str);
     } else {
         if draws > 0 {
             print!("[{}] {}", idx, format!("Draw {} cards and ", draws).blue());
@@ -216,7 +457,7 @@ pub fn pretty_print_move(
     }
 }
 
-fn card_pos(board: &Board, card: RawCard) -> String {
+pub(crate) fn card_pos(board: &Board, card: RawCard) -> String {
     let idx = board.board_cards.iter().position(|&x| x == card).unwrap();
     match idx {
         0 => "on board 1st row, 1st card".to_string(),
@@ -269,6 +510,87 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_parse_partial_board() {
+        let cards_str = "12jk".to_string();
+        let stack_str = "a?kq?".to_string();
+
+        let (cards, stack) = parse_partial_board(cards_str, stack_str).unwrap();
+
+        assert_eq!(
+            cards,
+            vec![RawCard(0), RawCard(1), RawCard(10), RawCard(12)]
+        );
+        assert_eq!(
+            stack,
+            vec![
+                Some(RawCard(13)),
+                None,
+                Some(RawCard(25)),
+                Some(RawCard(11)),
+                None
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_position() {
+        let cards_str = "1.j.k".to_string();
+        let stack_str = "aq".to_string();
+
+        let (cards, stack) = parse_position(cards_str, stack_str).unwrap();
+
+        assert_eq!(
+            cards,
+            vec![
+                Some(RawCard(0)),
+                None,
+                Some(RawCard(10)),
+                None,
+                Some(RawCard(12))
+            ]
+        );
+        assert_eq!(stack, vec![RawCard(13), RawCard(11)]);
+    }
+
+    #[test]
+    fn test_parse_deal_file() {
+        let text = "# The deal from the README
+      J
+     J 6
+    J 8 8
+   A 9 5 K     # Two kings so far
+  3 K A 10 2
+ J 4 Q 3 2 K
+
+T 7 6 7 Q K 7
+Stack: 6 8 4 8 10 A 5 5 Q 6 9 A
+       2 3 3 9 5 2 7 Q 4 4 9 0
+";
+        let (board, stack) = parse_deal_file(text).unwrap();
+
+        assert_eq!(board, "JJ6J88A95K3KA02J4Q32K0767QK7");
+        assert_eq!(stack, "68480A55Q69A2339527Q4490");
+    }
+
+    #[test]
+    fn test_parse_deal_file_errors() {
+        let error = parse_deal_file("J\nJ x\n").unwrap_err().to_string();
+        assert!(error.starts_with("Line 2, column 3: Unknown value (x)"));
+
+        let error = parse_deal_file("J\nJ 6\nJ 8\n").unwrap_err().to_string();
+        assert_eq!(
+            error,
+            "Line 3: row 3 of the pyramid should have 3 cards, but has 2"
+        );
+
+        let error = parse_deal_file("J\nJ 6\n").unwrap_err().to_string();
+        assert_eq!(
+            error,
+            "The pyramid should have 7 rows, but only 2 were given"
+        );
+    }
+
     #[test]
     fn test_verbosity_order() {
         assert!(Verbosity::Off < Verbosity::Low);
@@ -276,4 +598,59 @@ mod test {
         assert!(Verbosity::Medium < Verbosity::High);
         assert!(Verbosity::High < Verbosity::VeryHigh);
     }
+
+    #[test]
+    fn test_validate_position() {
+        let (board_cards, stack_cards) = parse_position(
+            "jj6j88a95k3ka02j4q32k07.7qk.".to_string(),
+            "68480a55q69a2339527q44".to_string(),
+        )
+        .unwrap();
+        assert!(validate_position(&board_cards, &stack_cards, 3).is_ok());
+        assert!(validate_position(&board_cards, &stack_cards, 23).is_err());
+
+        // The king at the end of the 6th row is still covered by the last two cards
+        let (board_cards, stack_cards) = parse_position(
+            "jj6j88a95k3ka02j4q32.0767qk7".to_string(),
+            "68480a55q69a2339527q44".to_string(),
+        )
+        .unwrap();
+        let error = validate_position(&board_cards, &stack_cards, 0)
+            .unwrap_err()
+            .to_string();
+        assert_eq!(
+            error,
+            concat!(
+                "The position isn't valid:\n",
+                "    Card 21 of the board has been removed, but card 27 covering it is still there"
+            )
+        );
+
+        let (board_cards, stack_cards) = parse_position(
+            "j...........................".to_string(),
+            "aaaa22223333444455556666777".to_string(),
+        )
+        .unwrap();
+        let error = validate_position(&board_cards, &stack_cards, 0)
+            .unwrap_err()
+            .to_string();
+        assert_eq!(
+            error,
+            "The position isn't valid:\n    The stack has 27 cards left, but it can only hold 24"
+        );
+
+        // Every problem is reported at once
+        let (board_cards, stack_cards) = parse_position(
+            ".j6j88a95k3ka02j4q32.0767qk7".to_string(),
+            "68480a55q69a2339527q44jj".to_string(),
+        )
+        .unwrap();
+        let error = validate_position(&board_cards, &stack_cards, 30)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("Card J is present 5 times"));
+        assert!(error.contains("The top of the pyramid has been removed"));
+        assert!(error.contains("Card 21 of the board has been removed"));
+        assert!(error.contains("The stack position is 30"));
+    }
 }
@@ -0,0 +1,231 @@
+use crate::game::board::Board;
+use crate::game::card::{Card, MatchType, RawCard};
+use crate::game::notation::{Location, MoveNotation};
+use crate::game::r#move::Move;
+use crate::game::utils::{cards_match, pretty_print_card};
+use anyhow::{bail, Result};
+
+/// What replaying a solution on a deal showed
+#[derive(Debug, Clone)]
+pub enum Verification {
+    /// Every move was legal and the game was won
+    Won { moves: i32 },
+    /// Every move was legal, but the game wasn't won
+    Unfinished { moves: i32, cards_left: usize },
+    /// A move broke the rules
+    Illegal {
+        /// The 1-based number of the move in the solution
+        move_num: usize,
+        notation: MoveNotation,
+        reason: String,
+        /// The board as it was before the move
+        board: Box<Board>,
+    },
+}
+
+impl Verification {
+    pub fn is_won(&self) -> bool {
+        matches!(self, Verification::Won { .. })
+    }
+}
+
+/// Play a solution on `board`, checking every move against the rules of the game
+///
+/// Unlike `replay_solution`, moves don't need to be among the ones `get_moves` gives, which leaves
+/// out moves the solver never plays. Moves made are counted the way the solver counts them. With
+/// `clear_all` set on the board, the stack has to be cleared as well to win.
+pub fn verify_solution(board: &Board, notations: &[MoveNotation]) -> Verification {
+    let mut board = board.clone();
+    for (idx, &notation) in notations.iter().enumerate() {
+        let r#move = match legal_move(&board, notation) {
+            Ok(r#move) => r#move,
+            Err(err) => {
+                return Verification::Illegal {
+                    move_num: idx + 1,
+                    notation,
+                    reason: err.to_string(),
+                    board: Box::new(board),
+                }
+            }
+        };
+        board.play_move(r#move);
+    }
+
+    if board.completed {
+        Verification::Won { moves: board.moves }
+    } else {
+        Verification::Unfinished {
+            moves: board.moves,
+            cards_left: board.cards_left(),
+        }
+    }
+}
+
+/// The move `notation` stands for on `board`, failing with the reason if it breaks the rules
+pub(crate) fn legal_move(board: &Board, notation: MoveNotation) -> Result<Move> {
+    if board.completed {
+        bail!("The game has already been won");
+    }
+
+    let stack_len = board.stack.len() as i32;
+    let draws = notation.draws as i32;
+    if draws > stack_len {
+        bail!(
+            "Drawing {} cards goes round the stack of {} cards more than once",
+            draws,
+            stack_len
+        );
+    }
+    let mut stack_idx = board.stack_idx + draws;
+    if stack_idx > stack_len {
+        stack_idx -= stack_len + 1;
+    }
+
+    let card_at = |location: Location| -> Result<RawCard> {
+        match location {
+            Location::Board { row, col } => {
+                let idx = (row as usize) * (row as usize - 1) / 2 + col as usize - 1;
+                if board.leaf_idxs.contains(&idx) {
+                    Ok(board.board_cards[idx])
+                } else if board.remaining_idxs().contains(&idx) {
+                    bail!(
+                        "The {} at {} is still covered",
+                        pretty_print_card(board.board_cards[idx], true),
+                        location
+                    )
+                } else {
+                    bail!("The card at {} has already been removed", location)
+                }
+            }
+            Location::Stack => match board.stack.get(stack_idx as usize) {
+                Some(&card) => Ok(card),
+                None => bail!("There's no card on the stack to play"),
+            },
+            Location::Waste if stack_idx > 0 => Ok(board.stack[stack_idx as usize - 1]),
+            Location::Waste => bail!("No card of the stack has been drawn to play"),
+        }
+    };
+
+    match notation.cards {
+        (location, None) => {
+            let card = card_at(location)?;
+            if Card::from(card).0 != 13 {
+                bail!(
+                    "The {} at {} isn't a king, so it needs a match",
+                    pretty_print_card(card, true),
+                    location
+                );
+            }
+            Ok(match location {
+                Location::Board { .. } => Move::new(MatchType::Board, draws, card, None),
+                // The solver counts the card left of the stack pointer as -1 draws
+                Location::Waste if draws == 0 => Move::new(MatchType::Stack, -1, card, None),
+                _ => Move::new(MatchType::Stack, draws, card, None),
+            })
+        }
+        (left_location, Some(right_location)) => {
+            if left_location == right_location {
+                bail!("A card can't be matched with itself");
+            }
+            let (left, right) = (card_at(left_location)?, card_at(right_location)?);
+            if !cards_match(left, right) {
+                bail!(
+                    "The {} at {} and the {} at {} don't add up to 13",
+                    pretty_print_card(left, true),
+                    left_location,
+                    pretty_print_card(right, true),
+                    right_location
+                );
+            }
+            Ok(match (left_location, right_location) {
+                (Location::Board { .. }, Location::Board { .. }) => {
+                    Move::new(MatchType::Board, draws, left, Some(right))
+                }
+                (Location::Board { .. }, _) => {
+                    Move::new(MatchType::BoardStack, draws, left, Some(right))
+                }
+                (_, Location::Board { .. }) => {
+                    Move::new(MatchType::BoardStack, draws, right, Some(left))
+                }
+                (Location::Waste, _) => Move::new(MatchType::Stack, draws, left, Some(right)),
+                _ => Move::new(MatchType::Stack, draws, right, Some(left)),
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::game::notation::{read_solution, write_solution};
+    use crate::test_utils::{readme_board, solve};
+
+    #[test]
+    fn test_verify_solution() {
+        let board = readme_board();
+        let solution = solve(&board);
+        let notations = read_solution(&write_solution(&board, &solution.moves_played)).unwrap();
+
+        match verify_solution(&board, &notations) {
+            Verification::Won { moves } => assert_eq!(moves, solution.moves),
+            verification => panic!("{:?}", verification),
+        }
+        match verify_solution(&board, &notations[..10]) {
+            Verification::Unfinished { cards_left, .. } => assert!(cards_left > 0),
+            verification => panic!("{:?}", verification),
+        }
+
+        let mut extra = notations.clone();
+        extra.push(notations[0]);
+        match verify_solution(&board, &extra) {
+            Verification::Illegal {
+                move_num, reason, ..
+            } => {
+                assert_eq!(move_num, notations.len() + 1);
+                assert_eq!(reason, "The game has already been won");
+            }
+            verification => panic!("{:?}", verification),
+        }
+    }
+
+    #[test]
+    fn test_illegal_moves() {
+        let board = readme_board();
+        let check =
+            |text: &str| legal_move(&board, text.parse().unwrap()).map_err(|err| err.to_string());
+
+        // Moves the solver leaves out are still legal, such as skipping the king
+        assert_eq!(
+            check("M R7C2+S").unwrap(),
+            Move::new(
+                MatchType::BoardStack,
+                0,
+                board.board_cards[22],
+                Some(board.stack[0])
+            )
+        );
+        assert!(check("M R7C1+R6C1")
+            .unwrap_err()
+            .contains("is still covered"));
+        assert!(check("M R7C1+R7C2")
+            .unwrap_err()
+            .contains("don't add up to 13"));
+        assert!(check("K R7C1").unwrap_err().contains("isn't a king"));
+        assert_eq!(
+            check("M R7C2+W").unwrap_err(),
+            "No card of the stack has been drawn to play"
+        );
+        assert_eq!(
+            check("D30 K S").unwrap_err(),
+            "Drawing 30 cards goes round the stack of 24 cards more than once"
+        );
+
+        let mut played = board.clone();
+        played.play_move(check("K R7C6").unwrap());
+        let error = legal_move(&played, "K R7C6".parse().unwrap()).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "The card at R7C6 has already been removed"
+        );
+    }
+}
@@ -1,7 +1,39 @@
 mod game;
+mod solver;
 pub mod validators;
 
+#[cfg(test)]
+mod test_utils;
+
 pub use game::board::Board;
+pub use game::card::{Card, MatchType, RawCard};
+pub use game::codec::{Compact, DealCodec, Standard, Unicode};
+pub use game::notation::{
+    read_solution, replay_solution, solution_board, write_solution, Location, MoveNotation,
+};
+pub use game::r#move::Move;
 pub use game::utils::{
-    parse_board, parse_verbosity, pretty_print_board, pretty_print_move, Verbosity,
+    parse_board, parse_deal_file, parse_partial_board, parse_position, parse_verbosity,
+    pretty_print_board, pretty_print_move, Verbosity,
+};
+pub use game::verify::{verify_solution, Verification};
+pub use solver::advisor::{advise_moves, MoveAdvice, PartialBoard};
+pub use solver::beam::beam_search;
+pub use solver::bfs::{simulate_games, Solution};
+pub use solver::checkpoint::Checkpoints;
+pub use solver::config::{AbortReason, CancellationToken, Heuristics, SearchOutcome, SolverConfig};
+pub use solver::dfs::depth_first_search;
+pub use solver::evaluate;
+pub use solver::external::external_search;
+pub use solver::learned::{
+    move_features, read_training_csv, training_examples, write_training_csv, MoveScorer,
+    TrainingExample, FEATURE_NAMES,
+};
+pub use solver::mcts::{mcts_search, Playout};
+pub use solver::optimal::{
+    find_optimal_solutions, find_optimal_solutions_checkpointed, OptimalSolutions,
 };
+pub use solver::optimize::{optimize_solution, Optimized};
+pub use solver::report::{json_advice, json_report, ReportDetails};
+pub use solver::stats::SearchStats;
+pub use solver::tune::{read_corpus, tune, TuneResult};
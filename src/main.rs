@@ -1,17 +1,272 @@
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use colored::Colorize;
-use pyrasol::validators::validate_board;
-use pyrasol::Board;
-use pyrasol::{parse_board, parse_verbosity, pretty_print_board, pretty_print_move, Verbosity};
-use rayon::prelude::*;
-use std::collections::HashSet;
-use std::sync::{Arc, Mutex};
+use pyrasol::evaluate::{
+    BlockedPairs, CardsCleared, Evaluator, ExposedLeaves, StackReachability, Weighted,
+};
+use pyrasol::validators::{analyze_deal, validate_deal, validate_position};
+use pyrasol::{
+    advise_moves, beam_search, depth_first_search, external_search,
+    find_optimal_solutions_checkpointed, mcts_search, optimize_solution, parse_board,
+    parse_deal_file, parse_partial_board, parse_position, parse_verbosity, pretty_print_board,
+    pretty_print_move, read_corpus, read_solution, read_training_csv, replay_solution,
+    simulate_games, solution_board, training_examples, tune, verify_solution, write_solution,
+    write_training_csv, Checkpoints, Heuristics, MoveScorer, PartialBoard, Playout, SolverConfig,
+    Verbosity, Verification,
+};
+use pyrasol::{
+    json_advice, json_report, AbortReason, Board, Compact, DealCodec, RawCard, ReportDetails,
+    SearchOutcome, SearchStats, Standard, Unicode,
+};
 
-use clap::Parser;
+use clap::builder::RangedU64ValueParser;
+use clap::{ArgGroup, Parser, Subcommand, ValueEnum};
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Searches other than the default one, which don't take its settings
+const OTHER_SEARCHES: [&str; 7] = [
+    "count_solutions",
+    "list_solutions",
+    "resume",
+    "beam_width",
+    "mcts_iterations",
+    "external",
+    "depth_first",
+];
+
+/// Searches that can't be given a time limit
+const UNTIMED_SEARCHES: [&str; 6] = [
+    "count_solutions",
+    "list_solutions",
+    "resume",
+    "beam_width",
+    "mcts_iterations",
+    "external",
+];
+
+/// Searches that stop at the first solution they find, instead of looking for every shortest one
+const SINGLE_SOLUTION_SEARCHES: [&str; 4] =
+    ["beam_width", "mcts_iterations", "external", "depth_first"];
 
 #[derive(Parser, Debug)]
-#[command(version, about)]
+#[command(
+    version,
+    about,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true,
+    group(
+        ArgGroup::new("search")
+            .args(["count_solutions", "beam_width", "mcts_iterations", "external", "depth_first"])
+            .multiple(false)
+    ),
+    group(
+        ArgGroup::new("count")
+            .args(["count_solutions", "list_solutions", "resume"])
+            .multiple(true)
+    )
+)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    deal: DealArgs,
+
+    /// Write the deal in another notation and stop
+    #[arg(long, value_enum)]
+    convert_to: Option<ConvertTo>,
+
+    /// Verbose output
+    ///
+    /// Repeat up to four times for different levels of verbosity
+    #[arg(long, short, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Max depth to simulate
+    #[arg(long, short, default_value_t = 60)]
+    max_depth: usize,
+
+    /// Increased options per iteration
+    ///
+    /// Run the search by playing additional options each iteration. By default the simulation will
+    /// only run all moves that require no card draws and then 2-3 moves that require draws.
+    /// Setting this flag will increase the options tried, but slow down the overall search.
+    #[arg(long, short, default_value_t = false)]
+    increased_options: bool,
+
+    /// Count all the shortest solutions
+    ///
+    /// Search exhaustively for every shortest solution instead of stopping at the first one found.
+    /// Solutions that only differ by the order of independent moves are counted once, which stops
+    /// after going through a million move orders, leaving a lower bound on the count. This can be
+    /// a lot slower than the default search.
+    #[arg(long, default_value_t = false)]
+    count_solutions: bool,
+
+    /// List the shortest solutions, up to the given number
+    ///
+    /// Implies --count-solutions
+    #[arg(long, conflicts_with_all = SINGLE_SOLUTION_SEARCHES)]
+    list_solutions: Option<usize>,
+
+    /// Save the progress of --count-solutions to this file, to be able to --resume it later
+    #[arg(long, requires = "count")]
+    checkpoint: Option<PathBuf>,
+
+    /// Least number of seconds between two checkpoints
+    #[arg(long, default_value_t = 300.0, requires = "count")]
+    checkpoint_interval: f64,
+
+    /// Carry on with --count-solutions from a checkpoint, instead of starting over
+    ///
+    /// Implies --count-solutions. The board, stack and max depth have to be the same as for the
+    /// search that saved the checkpoint. Unless --checkpoint is given as well, new checkpoints are
+    /// saved to the same file.
+    #[arg(long, conflicts_with_all = SINGLE_SOLUTION_SEARCHES)]
+    resume: Option<PathBuf>,
+
+    /// Stop searching after this many seconds
+    ///
+    /// The best solution found so far is shown, or if there is none, the line that clears the most
+    /// cards. Only the default search and --depth-first can be given a time limit.
+    #[arg(long, short, conflicts_with_all = UNTIMED_SEARCHES)]
+    time_limit: Option<f64>,
+
+    /// Give the same solution on every run
+    ///
+    /// By default the solution shown is whichever one a thread happened to find first. With this
+    /// flag, boards are ordered by the moves that led to them, which keeps more boards in memory.
+    #[arg(long, default_value_t = false, conflicts_with_all = OTHER_SEARCHES)]
+    deterministic: bool,
+
+    /// Print what the search did once it's done
+    #[arg(long, value_enum)]
+    stats: Option<StatsFormat>,
+
+    /// How to write the result
+    ///
+    /// With json, a single document with the deal, the moves of the solution, the final position,
+    /// the search statistics and whether the game was won is written, and nothing else. For a
+    /// stack with unknown cards, the document has the suggested moves instead.
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
+
+    /// Number of threads to search with, defaults to one per CPU
+    ///
+    /// Only the default search runs on more than one thread
+    #[arg(long, conflicts_with_all = OTHER_SEARCHES)]
+    threads: Option<usize>,
+
+    /// Order the moves of each board with the weights in this file
+    ///
+    /// The file has a feature name and a weight on each line, as written by the train command.
+    /// Moves are tried from the highest weighted sum of their features to the lowest, instead of
+    /// by the draws they need and the cards they remove.
+    #[arg(long, conflicts_with_all = OTHER_SEARCHES)]
+    move_scorer: Option<PathBuf>,
+
+    /// Give up once the search stores this many boards
+    ///
+    /// Only the default search can be given a limit on the boards it stores
+    #[arg(long, conflicts_with_all = OTHER_SEARCHES)]
+    max_states: Option<usize>,
+
+    /// Give up once the boards the search stores take up an estimated this many megabytes
+    ///
+    /// Only the default search can be given a limit on the memory it uses
+    #[arg(long, conflicts_with_all = OTHER_SEARCHES)]
+    max_memory: Option<usize>,
+
+    /// Use a beam search that keeps this many boards for each move count
+    ///
+    /// Instead of only trying the first few moves of every board, every move is tried from the
+    /// boards that the evaluator scores the highest.
+    #[arg(long, short, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    beam_width: Option<usize>,
+
+    /// How to score boards for the beam search
+    #[arg(long, value_enum, default_value_t = EvaluatorKind::Combined, requires = "beam_width")]
+    evaluator: EvaluatorKind,
+
+    /// Use Monte Carlo tree search with this many iterations per move
+    #[arg(long, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    mcts_iterations: Option<usize>,
+
+    /// The UCT exploration constant for Monte Carlo tree search
+    #[arg(long, default_value_t = 1.4, requires = "mcts_iterations")]
+    exploration: f64,
+
+    /// How games are played out in Monte Carlo tree search
+    #[arg(long, value_enum, default_value_t = PlayoutKind::Heuristic, requires = "mcts_iterations")]
+    playout: PlayoutKind,
+
+    /// Search depth first, raising the move limit one step at a time
+    ///
+    /// Uses a fixed amount of memory, set by --table-size, and finds one of the shortest
+    /// solutions. Respects --time-limit.
+    #[arg(long, default_value_t = false)]
+    depth_first: bool,
+
+    /// Number of positions the depth first search remembers
+    #[arg(long, default_value_t = 1 << 20, requires = "depth_first")]
+    table_size: usize,
+
+    /// Search every move, keeping the boards waiting to be played on disk
+    ///
+    /// Finds one of the shortest solutions like --count-solutions, but for deals whose search
+    /// doesn't fit in memory
+    #[arg(long, default_value_t = false)]
+    external: bool,
+
+    /// Directory for the files of the --external search, defaults to the system temp directory
+    #[arg(long, requires = "external")]
+    temp_dir: Option<PathBuf>,
+
+    /// Number of boards the --external search sorts in memory at a time
+    #[arg(long, default_value_t = 1 << 20, requires = "external")]
+    run_size: usize,
+
+    /// Write the solution found to this file, one move on each line
+    ///
+    /// Moves are written as the cards to draw and the cards to remove, such as D3 M R3C2+S, which
+    /// --replay reads back
+    #[arg(long)]
+    save_solution: Option<PathBuf>,
+
+    /// Play the moves of a solution file on the deal instead of searching
+    ///
+    /// The moves are played from the position saved at the start of the file, if there is one.
+    /// With --optimize, the shorter line can be saved again with --save-solution.
+    #[arg(long)]
+    replay: Option<PathBuf>,
+
+    /// Shorten the solution found by replacing runs of moves with cheaper ones
+    ///
+    /// Runs of up to --optimize-window moves are replaced when the same position can be reached
+    /// in fewer moves made, such as by playing independent moves in another order or drawing
+    /// fewer cards. Lines that don't win are left as they are.
+    #[arg(long, default_value_t = false)]
+    optimize: bool,
+
+    /// Longest run of moves --optimize tries to replace
+    #[arg(long, default_value_t = 4)]
+    optimize_window: usize,
+
+    /// Number of stack orders to sample when the stack has unknown cards
+    ///
+    /// The samples are searched like the default search, with the same threads, move scorer and
+    /// limits. The time limit is for all of them together, while --max-states and --max-memory
+    /// hold for each search on its own.
+    #[arg(long, default_value_t = 20)]
+    samples: usize,
+}
+
+// The deal to solve, or to check a solution against
+#[derive(clap::Args, Debug)]
+struct DealArgs {
     /// The board to solve
     ///
     /// The board to solve as a string of characters, where each character represents a card.
@@ -20,9 +275,12 @@ struct Args {
     /// to bottom.
     /// Note: Ace can be either 1 or a
     ///
+    /// For a game in progress, cards already removed from the board are given as .
+    ///
     /// Example:
     ///     jj6j88a95k3ka02j4q32k0767qk7
-    board: String,
+    #[arg(required_unless_present_any = ["file", "position"])]
+    board: Option<String>,
 
     /// The stack
     ///
@@ -31,219 +289,1037 @@ struct Args {
     /// represented as 0, j, q, k, and a respectively. Cards are read from left to right.
     /// Note: Ace can be either 1 or a
     ///
+    /// Cards that are still face down can be given as ?, in which case the best next move is
+    /// suggested instead of solving the whole game.
+    ///
+    /// For a game in progress, only the cards left in the stack are given.
+    ///
     /// Example:
     ///     68480a55q69a2339527q4490
-    stack: String,
+    #[arg(required_unless_present_any = ["file", "position"])]
+    stack: Option<String>,
+
+    /// Read the board and stack from a file instead, or from stdin when given -
+    ///
+    /// The file shows the pyramid as 7 rows with the stack on the lines after it. Cards can be
+    /// separated by whitespace, 10 can be given as 10, T or 0, and anything after a # is a comment.
+    #[arg(long, short, conflicts_with_all = ["board", "stack"])]
+    file: Option<PathBuf>,
+
+    /// Notation the board and stack are given in
+    ///
+    /// Games in progress and stacks with unknown cards can only be given in the compact notation,
+    /// and --file has a notation of its own
+    #[arg(long, value_enum, default_value_t = Notation::Compact, conflicts_with_all = ["file", "position"])]
+    notation: Notation,
+
+    /// Start from a position written with --convert-to canonical, instead of a board and stack
+    #[arg(long, conflicts_with_all = ["board", "stack", "file"])]
+    position: Option<String>,
+
+    /// Cards of the stack already drawn, for a game in progress
+    ///
+    /// The last card drawn and the one after it can be played without drawing
+    #[arg(long, default_value_t = 0)]
+    stack_position: usize,
+
+    /// Moves already made, including draws, for a game in progress
+    #[arg(long, default_value_t = 0, value_parser = clap::value_parser!(i32).range(0..))]
+    moves_made: i32,
 
     /// Clear all the cards, including the stack
     #[arg(long, short, default_value_t = false)]
     clear_all: bool,
+}
 
-    /// Verbose output
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Try different settings of the default search over a corpus of deals
     ///
-    /// Repeat up to four times for different levels of verbosity
-    #[arg(long, short, action = clap::ArgAction::Count)]
-    verbose: u8,
+    /// Every setting is run over all the deals, and the solve rate, average solution length and
+    /// runtime of each is shown, best first.
+    Tune {
+        /// File with a deal on each line, as a board and a stack separated by a space
+        ///
+        /// Empty lines and lines starting with # are skipped
+        corpus: PathBuf,
 
-    /// Max depth to simulate
-    #[arg(long, short, default_value_t = 60)]
-    max_depth: usize,
+        /// Try this many settings picked at random, instead of every combination
+        #[arg(long)]
+        random: Option<usize>,
 
-    /// Increased options per iteration
+        /// Max depth to simulate
+        #[arg(long, short, default_value_t = 60)]
+        max_depth: usize,
+
+        /// Seconds each deal gets with each setting
+        #[arg(long, short, default_value_t = 10.0)]
+        time_limit: f64,
+
+        /// Verbose output
+        #[arg(long, short, action = clap::ArgAction::Count)]
+        verbose: u8,
+    },
+    /// Solve a corpus of deals and write every move along the solutions as training data
     ///
-    /// Run the search by playing additional options each iteration. By default the simulation will
-    /// only run all moves that require no card draws and then 2-3 moves that require draws.
-    /// Setting this flag will increase the options tried, but slow down the overall search.
-    #[arg(long, short, default_value_t = false)]
-    increased_options: bool,
+    /// Each row of the CSV has the features of a move, whether it was played, whether the deal was
+    /// solved and how many moves the solution still made.
+    ExportTraining {
+        /// File with a deal on each line, as a board and a stack separated by a space
+        corpus: PathBuf,
+
+        /// CSV file to write
+        #[arg(long, short)]
+        output: PathBuf,
+
+        /// Max depth to simulate
+        #[arg(long, short, default_value_t = 60)]
+        max_depth: usize,
+
+        /// Seconds each deal gets
+        #[arg(long, short, default_value_t = 10.0)]
+        time_limit: f64,
+    },
+    /// Check a solution against a deal, playing every move by the rules of the game
+    ///
+    /// The first illegal move is shown with the board as it was at that point. Fails unless every
+    /// move is legal and the game is won.
+    ///
+    /// The deal is given the same way as for solving it. Solutions saved with --clear-all say so
+    /// in the position at their start, so --clear-all is only needed for solutions without one.
+    Verify {
+        /// Solution file with a move on each line, as written by --save-solution
+        solution: PathBuf,
+
+        #[command(flatten)]
+        deal: DealArgs,
+    },
+    /// Fit move scorer weights to training data written by export-training
+    Train {
+        /// CSV file written by export-training
+        data: PathBuf,
+
+        /// File to write the weights to, for --move-scorer
+        #[arg(long, short)]
+        output: PathBuf,
+
+        /// Passes over the training data
+        #[arg(long, default_value_t = 500)]
+        epochs: usize,
+
+        #[arg(long, default_value_t = 0.1)]
+        learning_rate: f64,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum EvaluatorKind {
+    /// Fewer cards left is better
+    CardsCleared,
+    /// More exposed cards on the board is better
+    ExposedLeaves,
+    /// Fewer cards covering their own matches is better
+    BlockedPairs,
+    /// More exposed cards with a match in the stack is better
+    StackReachability,
+    /// A weighted mix of all the others
+    Combined,
+}
+
+impl EvaluatorKind {
+    fn evaluator(self) -> Box<dyn Evaluator> {
+        match self {
+            EvaluatorKind::CardsCleared => Box::new(CardsCleared),
+            EvaluatorKind::ExposedLeaves => Box::new(ExposedLeaves),
+            EvaluatorKind::BlockedPairs => Box::new(BlockedPairs),
+            EvaluatorKind::StackReachability => Box::new(StackReachability),
+            EvaluatorKind::Combined => Box::new(Weighted::default()),
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum StatsFormat {
+    /// A summary table
+    Table,
+    /// A single line JSON object
+    Json,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum Notation {
+    /// A character for each card, such as jj6j88a95k
+    Compact,
+    /// A rank and a suit for each card, such as JS JH 6S
+    Standard,
+    /// A rank and a suit symbol for each card, such as J♠ J♥ 6♠
+    Symbols,
+    /// A playing card character for each card, such as 🂫🂻🂦
+    Unicode,
+}
+
+impl Notation {
+    fn codec(self) -> Box<dyn DealCodec> {
+        match self {
+            Notation::Compact => Box::new(Compact),
+            Notation::Standard => Box::new(Standard {
+                suit_symbols: false,
+            }),
+            Notation::Symbols => Box::new(Standard { suit_symbols: true }),
+            Notation::Unicode => Box::new(Unicode),
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum ConvertTo {
+    /// A character for each card, such as jj6j88a95k
+    Compact,
+    /// A rank and a suit for each card, such as JS JH 6S
+    Standard,
+    /// A rank and a suit symbol for each card, such as J♠ J♥ 6♠
+    Symbols,
+    /// A playing card character for each card, such as 🂫🂻🂦
+    Unicode,
+    /// The position, including stack cards drawn and moves made, which --position reads back
+    Canonical,
+}
+
+impl ConvertTo {
+    /// The notation to write a new deal in, or nothing for the canonical position
+    fn notation(self) -> Option<Notation> {
+        match self {
+            ConvertTo::Compact => Some(Notation::Compact),
+            ConvertTo::Standard => Some(Notation::Standard),
+            ConvertTo::Symbols => Some(Notation::Symbols),
+            ConvertTo::Unicode => Some(Notation::Unicode),
+            ConvertTo::Canonical => None,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum OutputFormat {
+    /// Sentences for people to read
+    Text,
+    /// A single JSON document
+    Json,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum PlayoutKind {
+    /// Play random moves
+    Random,
+    /// Mostly play the move needing the fewest draws
+    Heuristic,
+}
+
+impl From<PlayoutKind> for Playout {
+    fn from(kind: PlayoutKind) -> Self {
+        match kind {
+            PlayoutKind::Random => Playout::Random,
+            PlayoutKind::Heuristic => Playout::Heuristic,
+        }
+    }
 }
 
 fn main() -> Result<()> {
     let args = Args::parse();
-    let verbosity = parse_verbosity(args.verbose);
 
-    let (board_cards, stack_cards) = parse_board(args.board, args.stack)?;
-    validate_board(&board_cards, &stack_cards)?;
-    let leaf_idxs: Vec<usize> = vec![21, 22, 23, 24, 25, 26, 27];
-    let board = Board::new(board_cards, stack_cards, leaf_idxs, args.clear_all);
+    match args.command {
+        Some(Command::Tune {
+            corpus,
+            random,
+            max_depth,
+            time_limit,
+            verbose,
+        }) => return run_tune(&corpus, random, max_depth, time_limit, verbose),
+        Some(Command::ExportTraining {
+            corpus,
+            output,
+            max_depth,
+            time_limit,
+        }) => return run_export_training(&corpus, &output, max_depth, time_limit),
+        Some(Command::Verify { solution, deal }) => return run_verify(&deal, &solution),
+        Some(Command::Train {
+            data,
+            output,
+            epochs,
+            learning_rate,
+        }) => return run_train(&data, &output, epochs, learning_rate),
+        None => {}
+    }
 
-    pretty_print_board(&board);
+    if args.convert_to.is_some() && args.format == OutputFormat::Json {
+        bail!("--convert-to writes the deal as text, so it can't be used with --format json");
+    }
 
-    let (first_top_moves, first_games, top_moves) = if args.increased_options {
-        (5, 10, 3)
+    let verbosity = match args.format {
+        OutputFormat::Text => parse_verbosity(args.verbose),
+        OutputFormat::Json => Verbosity::Off,
+    };
+    let heuristics = if args.increased_options {
+        Heuristics::INCREASED
     } else {
-        (3, 5, 2)
+        Heuristics::DEFAULT
     };
+    let board = match read_board(&args, &heuristics)? {
+        Some(board) => board,
+        // Moves were suggested for a stack with unknown cards instead
+        None => return Ok(()),
+    };
+    if let Some(convert_to) = args.convert_to {
+        if let Some(notation) = convert_to.notation() {
+            bail!(
+                "Only a new deal can be written as {}, use canonical for a game in progress",
+                notation.codec().name()
+            );
+        }
+        println!("{}", board.to_canonical());
+        return Ok(());
+    }
+
+    if let Some(path) = &args.replay {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Couldn't read {}", path.display()))?;
+        let board = solution_board(&board, &text)
+            .with_context(|| format!("{} doesn't go with the deal", path.display()))?;
+        let notations = read_solution(&text)
+            .with_context(|| format!("Couldn't read the solution from {}", path.display()))?;
+        if args.format == OutputFormat::Text {
+            pretty_print_board(&board);
+        }
+        let moves_played = replay_solution(&board, &notations)?;
+        finish_solution(
+            &args,
+            board,
+            moves_played,
+            ReportDetails::default(),
+            verbosity,
+        )?;
+        return Ok(());
+    }
+
+    // Every move would go past the max depth, so the search couldn't play any of them
+    if board.moves >= args.max_depth as i32 {
+        bail!(
+            "{} moves have already been made, so a max depth of {} leaves no room for more",
+            board.moves,
+            args.max_depth
+        );
+    }
+
+    if args.format == OutputFormat::Text {
+        pretty_print_board(&board);
+    }
+
+    if args.count_solutions || args.list_solutions.is_some() || args.resume.is_some() {
+        let max_solutions = args.list_solutions.unwrap_or(usize::MAX);
+        let checkpoints = args
+            .checkpoint
+            .clone()
+            .or(args.resume.clone())
+            .map(|path| Checkpoints {
+                path,
+                interval: Duration::from_secs_f64(args.checkpoint_interval),
+                resume: args.resume.clone(),
+            });
+        let (optimal, stats) = find_optimal_solutions_checkpointed(
+            board.clone(),
+            args.max_depth,
+            max_solutions,
+            checkpoints.as_ref(),
+            verbosity,
+        )?;
+        if args.format == OutputFormat::Json {
+            let solution = optimal
+                .as_ref()
+                .and_then(|optimal| optimal.solutions.first().cloned())
+                .unwrap_or_default();
+            let details = ReportDetails {
+                stats: Some(&stats),
+                optimal: optimal.as_ref(),
+                ..ReportDetails::default()
+            };
+            return finish_solution(&args, board, solution, details, verbosity);
+        }
+        match optimal {
+            Some(optimal) => {
+                print_text(
+                    &args,
+                    format!(
+                        "{}{} distinct shortest solutions with {} moves made ({} move orders)",
+                        if optimal.truncated { "At least " } else { "" },
+                        optimal.solutions.len(),
+                        optimal.moves,
+                        optimal.orderings
+                    )
+                    .green(),
+                );
+                if let Some(solution) = optimal.solutions.first() {
+                    save_solution(args.save_solution.as_deref(), &board, solution)?;
+                }
+                if args.list_solutions.is_some() {
+                    for (idx, solution) in optimal.solutions.into_iter().enumerate() {
+                        println!();
+                        println!("Solution {}", idx + 1);
+                        describe_solution(board.clone(), solution, verbosity);
+                    }
+                }
+            }
+            None => print_text(
+                &args,
+                format!("No solution found with a max depth of {}", args.max_depth).red(),
+            ),
+        }
+        print_stats(&args, &stats);
+        return Ok(());
+    }
+
+    if let Some(iterations) = args.mcts_iterations {
+        let (solution, stats) = mcts_search(
+            board.clone(),
+            args.max_depth,
+            iterations,
+            args.exploration,
+            args.playout.into(),
+            &mut rand::thread_rng(),
+            verbosity,
+        );
+        if solution.is_partial() {
+            print_text(
+                &args,
+                format!(
+                    "No solution found with {} iterations per move, best line leaves {} cards",
+                    iterations, solution.cards_left
+                )
+                .red(),
+            );
+        } else {
+            print_text(
+                &args,
+                format!("Solution found with {} moves made", solution.moves).green(),
+            );
+        }
+        let details = ReportDetails {
+            stats: Some(&stats),
+            ..ReportDetails::default()
+        };
+        finish_solution(&args, board, solution.moves_played, details, verbosity)?;
+        return Ok(());
+    }
+
+    if args.external {
+        let (solution, stats) = external_search(
+            board.clone(),
+            args.max_depth,
+            args.run_size,
+            args.temp_dir.as_deref(),
+            verbosity,
+        )?;
+        if solution.is_partial() {
+            print_text(
+                &args,
+                format!(
+                    "No solution found with a max depth of {}, best line leaves {} cards",
+                    args.max_depth, solution.cards_left
+                )
+                .red(),
+            );
+        } else {
+            print_text(
+                &args,
+                format!("Solution found with {} moves made", solution.moves).green(),
+            );
+        }
+        let details = ReportDetails {
+            stats: Some(&stats),
+            ..ReportDetails::default()
+        };
+        finish_solution(&args, board, solution.moves_played, details, verbosity)?;
+        return Ok(());
+    }
+
+    if args.depth_first {
+        let deadline = args
+            .time_limit
+            .map(|time_limit| Instant::now() + Duration::from_secs_f64(time_limit));
+        let (solution, stats) = depth_first_search(
+            board.clone(),
+            args.max_depth,
+            args.table_size,
+            deadline,
+            verbosity,
+        );
+        let out_of_time =
+            solution.is_partial() && deadline.is_some_and(|deadline| Instant::now() >= deadline);
+        if !solution.is_partial() {
+            print_text(
+                &args,
+                format!("Solution found with {} moves made", solution.moves).green(),
+            );
+        } else if out_of_time {
+            print_text(
+                &args,
+                format!(
+                    "No solution found within the time limit, best line leaves {} cards",
+                    solution.cards_left
+                )
+                .red(),
+            );
+        } else {
+            print_text(
+                &args,
+                format!(
+                    "No solution found with a max depth of {}, best line leaves {} cards",
+                    args.max_depth, solution.cards_left
+                )
+                .red(),
+            );
+        }
+        let details = ReportDetails {
+            aborted: out_of_time.then_some(AbortReason::TimeLimit),
+            stats: Some(&stats),
+            ..ReportDetails::default()
+        };
+        finish_solution(&args, board, solution.moves_played, details, verbosity)?;
+        return Ok(());
+    }
+
+    if let Some(beam_width) = args.beam_width {
+        let (solution, stats) = beam_search(
+            board.clone(),
+            args.max_depth,
+            beam_width,
+            args.evaluator.evaluator().as_ref(),
+            verbosity,
+        );
+        if solution.is_partial() {
+            print_text(
+                &args,
+                format!(
+                    "No solution found with a beam width of {}, best line leaves {} cards",
+                    beam_width, solution.cards_left
+                )
+                .red(),
+            );
+        } else {
+            print_text(
+                &args,
+                format!("Solution found with {} moves made", solution.moves).green(),
+            );
+        }
+        let details = ReportDetails {
+            stats: Some(&stats),
+            ..ReportDetails::default()
+        };
+        finish_solution(&args, board, solution.moves_played, details, verbosity)?;
+        return Ok(());
+    }
 
-    let solution = simulate_games(
+    let config = solver_config(&args)?;
+    let (outcome, stats) = simulate_games(
         board.clone(),
         args.max_depth,
-        top_moves,
-        first_top_moves,
-        first_games,
+        &heuristics,
+        &config,
         verbosity,
     )?;
 
-    describe_solution(board, solution, verbosity);
+    let aborted = match &outcome {
+        SearchOutcome::Done(solution) if !solution.is_partial() => {
+            print_text(
+                &args,
+                format!("Solution found with {} moves made", solution.moves).green(),
+            );
+            None
+        }
+        SearchOutcome::Done(solution) => {
+            print_text(
+                &args,
+                format!(
+                    "No solution found with a max depth of {}, best line leaves {} cards",
+                    args.max_depth, solution.cards_left
+                )
+                .red(),
+            );
+            None
+        }
+        SearchOutcome::Aborted { reason, best } => {
+            print_text(
+                &args,
+                format!(
+                    "No solution found, the search stopped because {}, best line leaves {} cards",
+                    reason, best.cards_left
+                )
+                .red(),
+            );
+            Some(*reason)
+        }
+    };
+    let solution = outcome.into_solution();
+    let details = ReportDetails {
+        aborted,
+        stats: Some(&stats),
+        ..ReportDetails::default()
+    };
+    finish_solution(&args, board, solution.moves_played, details, verbosity)
+}
 
-    Ok(())
+/// The threads, limits and move ordering the searches run with
+fn solver_config(args: &Args) -> Result<SolverConfig> {
+    Ok(SolverConfig {
+        threads: args.threads,
+        max_states: args.max_states,
+        max_bytes: args.max_memory.map(|megabytes| megabytes * 1024 * 1024),
+        time_limit: args.time_limit.map(Duration::from_secs_f64),
+        cancel: None,
+        deterministic: args.deterministic,
+        move_scorer: args
+            .move_scorer
+            .as_deref()
+            .map(MoveScorer::load)
+            .transpose()?,
+    })
 }
 
-fn describe_solution(mut board: Board, solution: Vec<usize>, verbosity: Verbosity) {
-    let mut moves_made: i32 = 0;
-    for move_num in solution.iter() {
-        if verbosity >= Verbosity::High {
-            pretty_print_board(&board);
-        }
+/// Read the board and stack given as arguments or in a file, or the position given instead
+///
+/// Nothing is returned when there's nothing left to solve, as the stack has unknown cards and the
+/// best moves were suggested instead, or the deal was converted to another notation
+fn read_board(args: &Args, heuristics: &Heuristics) -> Result<Option<Board>> {
+    let deal = &args.deal;
+    if let Some(position) = &deal.position {
+        return Ok(Some(Board::from_canonical(position)?));
+    }
+    let (board, stack) = deal_strings(deal)?;
 
-        let moves = board.get_moves();
-        if moves.is_empty() {
-            panic!("No moves left?");
+    if stack.contains('?') {
+        let (board_cards, stack_cards) = parse_partial_board(board, stack)?;
+        let partial = PartialBoard::new(board_cards, stack_cards, deal.clear_all)?;
+
+        let mut rng = rand::thread_rng();
+        let (advice, aborted) = advise_moves(
+            &partial,
+            args.samples,
+            args.max_depth,
+            heuristics,
+            &solver_config(args)?,
+            &mut rng,
+        )?;
+        let board = partial.sample(&mut rng);
+        if args.format == OutputFormat::Json {
+            println!("{}", json_advice(&board, &advice, aborted));
+            return Ok(None);
+        }
+        if let Some(reason) = aborted {
+            println!(
+                "{}",
+                format!("Stopped sampling early because {}", reason).yellow()
+            );
+        }
+        if advice.is_empty() {
+            println!("{}", "No moves available with the known cards".red());
+            return Ok(None);
         }
 
-        if verbosity >= Verbosity::VeryHigh {
-            println!("Available moves:");
-            for (idx, r#move) in moves.iter().enumerate() {
-                if idx + 1 == *move_num {
-                    print!("> ");
-                }
-                pretty_print_move(&board, idx as u8 + 1, *r#move, true);
-            }
+        println!("Suggested moves, best first:");
+        for (idx, move_advice) in advice.iter().enumerate() {
+            pretty_print_move(&board, idx as i32 + 1, move_advice.r#move, false);
+            println!(
+                "    win rate {:.0}% ({} of {} samples)",
+                move_advice.win_rate() * 100.0,
+                move_advice.wins,
+                move_advice.samples
+            );
         }
+        return Ok(None);
+    }
 
-        let r#move = match moves.get(*move_num - 1) {
-            Some(x) => x,
-            None => panic!("Move {} not found in moves: {:?}", move_num, moves),
-        };
+    if in_progress(deal, &board) {
+        return Ok(Some(position_board(deal, board, stack)?));
+    }
 
-        if verbosity < Verbosity::VeryHigh {
-            pretty_print_move(&board, moves_made as u8, *r#move, true);
+    let (board_cards, stack_cards) = deal_cards(deal, &board, &stack)?;
+    if let Some(notation) = args.convert_to.and_then(ConvertTo::notation) {
+        let (board, stack) = notation.codec().encode(&board_cards, &stack_cards);
+        println!("{}\n{}", board, stack);
+        return Ok(None);
+    }
+
+    analyze_deal(&board_cards, &stack_cards, deal.clear_all)?;
+    let leaf_idxs: Vec<usize> = vec![21, 22, 23, 24, 25, 26, 27];
+    Ok(Some(Board::new(
+        board_cards,
+        stack_cards,
+        leaf_idxs,
+        deal.clear_all,
+    )))
+}
+
+/// Read the deal for `verify`, which has to have every card known
+fn read_verified_deal(deal: &DealArgs) -> Result<Board> {
+    if let Some(position) = &deal.position {
+        return Board::from_canonical(position);
+    }
+    let (board, stack) = deal_strings(deal)?;
+    if stack.contains('?') {
+        bail!("A solution can only be checked against a deal with every card known");
+    }
+
+    if in_progress(deal, &board) {
+        return position_board(deal, board, stack);
+    }
+    let (board_cards, stack_cards) = deal_cards(deal, &board, &stack)?;
+    let leaf_idxs: Vec<usize> = vec![21, 22, 23, 24, 25, 26, 27];
+    Ok(Board::new(
+        board_cards,
+        stack_cards,
+        leaf_idxs,
+        deal.clear_all,
+    ))
+}
+
+/// The board and stack as given as arguments or in a file
+fn deal_strings(deal: &DealArgs) -> Result<(String, String)> {
+    let (board, stack) = match &deal.file {
+        Some(path) => read_deal_file(path)?,
+        // Both are required without a file or a position
+        None => (deal.board.clone().unwrap(), deal.stack.clone().unwrap()),
+    };
+    if deal.notation != Notation::Compact && (in_progress(deal, &board) || stack.contains('?')) {
+        bail!("Games in progress and stacks with unknown cards can only be given in the compact notation");
+    }
+    Ok((board, stack))
+}
+
+fn in_progress(deal: &DealArgs, board: &str) -> bool {
+    board.contains('.') || deal.stack_position > 0 || deal.moves_made > 0
+}
+
+/// The board of a game in progress
+fn position_board(deal: &DealArgs, board: String, stack: String) -> Result<Board> {
+    let (board_cards, stack_cards) = parse_position(board, stack)?;
+    validate_position(&board_cards, &stack_cards, deal.stack_position)?;
+    Ok(Board::from_position(
+        &board_cards,
+        stack_cards,
+        deal.stack_position as i32,
+        deal.moves_made,
+        deal.clear_all,
+    ))
+}
+
+/// The cards of a new deal, in the notation it was given in
+fn deal_cards(deal: &DealArgs, board: &str, stack: &str) -> Result<(Vec<RawCard>, Vec<RawCard>)> {
+    match deal.notation {
+        Notation::Compact => {
+            validate_deal(board, stack)?;
+            parse_board(board.to_string(), stack.to_string())
         }
+        notation => {
+            let (board_cards, stack_cards) = notation.codec().decode(board, stack)?;
+            // The compact form gets every problem with the deal reported
+            let (board, stack) = Compact.encode(&board_cards, &stack_cards);
+            validate_deal(&board, &stack)?;
+            Ok((board_cards, stack_cards))
+        }
+    }
+}
 
-        board.play_move(*r#move);
+fn read_deal_file(path: &Path) -> Result<(String, String)> {
+    let (text, name) = if path == Path::new("-") {
+        let mut text = String::new();
+        io::stdin()
+            .read_to_string(&mut text)
+            .context("Couldn't read the deal from stdin")?;
+        (text, "stdin".to_string())
+    } else {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Couldn't read {}", path.display()))?;
+        (text, path.display().to_string())
+    };
+    parse_deal_file(&text).with_context(|| format!("Couldn't read the deal from {}", name))
+}
 
-        moves_made += 1 + r#move.1;
+fn read_deals(corpus: &Path) -> Result<Vec<Board>> {
+    let text = fs::read_to_string(corpus)
+        .with_context(|| format!("Couldn't read {}", corpus.display()))?;
+    let deals = read_corpus(&text)?;
+    if deals.is_empty() {
+        bail!("{} has no deals", corpus.display());
     }
-    println!("[{}] {}", board.moves, "All done!".green());
+    Ok(deals)
 }
 
-fn simulate_games(
-    board: Board,
+fn run_export_training(
+    corpus: &Path,
+    output: &Path,
     max_depth: usize,
-    top_moves: usize,
-    first_top_moves: usize,
-    first_games: usize,
-    verbosity: Verbosity,
-) -> Result<Vec<usize>> {
-    let seen_states = Arc::new(Mutex::new(HashSet::new()));
-
-    // Pre-create 60 queues for different move counts
-    let queues = Arc::new(Mutex::new(vec![vec![]; max_depth]));
+    time_limit: f64,
+) -> Result<()> {
+    let config = SolverConfig {
+        time_limit: Some(Duration::from_secs_f64(time_limit)),
+        ..SolverConfig::default()
+    };
+    let mut examples = vec![];
+    for board in read_deals(corpus)? {
+        let (outcome, _) = simulate_games(
+            board.clone(),
+            max_depth,
+            &Heuristics::DEFAULT,
+            &config,
+            Verbosity::Off,
+        )?;
+        examples.extend(training_examples(&board, outcome.solution()));
+    }
 
-    // Initial board at 0 moves
-    queues
-        .lock()
-        .unwrap()
-        .get_mut(0)
-        .unwrap()
-        .push((board, vec![]));
+    let file =
+        File::create(output).with_context(|| format!("Couldn't create {}", output.display()))?;
+    let mut writer = BufWriter::new(file);
+    write_training_csv(&mut writer, &examples)?;
+    writer.flush()?;
+    println!("Wrote {} moves to {}", examples.len(), output.display());
+    Ok(())
+}
 
-    let mut queue_num = 0;
+fn run_train(data: &Path, output: &Path, epochs: usize, learning_rate: f64) -> Result<()> {
+    let text =
+        fs::read_to_string(data).with_context(|| format!("Couldn't read {}", data.display()))?;
+    let examples = read_training_csv(&text)?;
+    let scorer = MoveScorer::fit(&examples, epochs, learning_rate);
+    fs::write(output, scorer.to_text())
+        .with_context(|| format!("Couldn't write {}", output.display()))?;
+    print!("{}", scorer.to_text());
+    Ok(())
+}
 
-    let duplicates = Arc::new(Mutex::new(0));
-    let total_moves_played = Arc::new(Mutex::new(0));
+fn run_verify(deal: &DealArgs, solution: &Path) -> Result<()> {
+    let deal = read_verified_deal(deal)?;
 
-    while queue_num < max_depth {
-        let mut queue = queues.lock().unwrap().get(queue_num).unwrap().clone();
-        let queue_size = queue.len();
+    let text = fs::read_to_string(solution)
+        .with_context(|| format!("Couldn't read {}", solution.display()))?;
+    let board = solution_board(&deal, &text)
+        .with_context(|| format!("{} doesn't go with the deal", solution.display()))?;
+    let notations = read_solution(&text)
+        .with_context(|| format!("Couldn't read the solution from {}", solution.display()))?;
 
-        if verbosity == Verbosity::Low {
+    match verify_solution(&board, &notations) {
+        Verification::Won { moves } => {
             println!(
-                "Queue {} - size: {}",
-                queue_num,
-                queue_size,
-            );
-        } else if verbosity >= Verbosity::Medium {
-            println!(
-                "Queue {} - size: {} - total moves played: {} - duplicates: {}",
-                queue_num,
-                queue_size,
-                *total_moves_played.lock().unwrap(),
-                *duplicates.lock().unwrap()
+                "{}",
+                format!(
+                    "The solution wins the game in {} moves ({} moves made)",
+                    notations.len(),
+                    moves
+                )
+                .green()
             );
+            Ok(())
         }
+        Verification::Unfinished { moves, cards_left } => bail!(
+            "Every move is legal, but {} cards are left after {} moves made",
+            cards_left,
+            moves
+        ),
+        Verification::Illegal {
+            move_num,
+            notation,
+            reason,
+            board,
+        } => {
+            println!("Board before move {}:", move_num);
+            pretty_print_board(&board);
+            bail!("Move {} ({}) is illegal: {}", move_num, notation, reason)
+        }
+    }
+}
 
-        let result = queue.par_drain(..).find_map_any(|(board, moves_made)| {
-            if board.completed {
-                println!(
-                    "{}",
-                    format!("Solution found with {} moves made", board.moves).green()
-                );
-                return Some(moves_made.clone());
-            }
+fn run_tune(
+    corpus: &Path,
+    random: Option<usize>,
+    max_depth: usize,
+    time_limit: f64,
+    verbose: u8,
+) -> Result<()> {
+    let deals = read_deals(corpus)?;
+    let settings = match random {
+        Some(count) => {
+            let mut rng = rand::thread_rng();
+            (0..count).map(|_| Heuristics::random(&mut rng)).collect()
+        }
+        None => Heuristics::grid(),
+    };
+    println!(
+        "Trying {} settings over {} deals",
+        settings.len(),
+        deals.len()
+    );
 
-            let moves = board.get_moves();
-            if moves.is_empty() {
-                return None;
-            }
-            // Sort first by draws and then by the card being removed
+    let mut results = tune(
+        &deals,
+        &settings,
+        max_depth,
+        Duration::from_secs_f64(time_limit),
+        parse_verbosity(verbose),
+    );
+    results.sort_by(|a, b| {
+        b.solve_rate()
+            .total_cmp(&a.solve_rate())
+            .then(a.average_moves().total_cmp(&b.average_moves()))
+            .then(a.elapsed.cmp(&b.elapsed))
+    });
 
-            let max_moves = if board.moves as usize <= first_games {
-                first_top_moves
-            } else {
-                top_moves
+    println!(
+        "{:>10}  {:>11}  {:>9}  {:>10}  {:>11}  {:>10}",
+        "Top moves", "First moves", "First top", "Solve rate", "Avg. moves", "Time (s)"
+    );
+    for result in results {
+        let row = format!(
+            "{:>10}  {:>11}  {:>9}  {:>9.1}%  {:>11.2}  {:>10.2}",
+            result.heuristics.top_moves,
+            result.heuristics.first_games,
+            result.heuristics.first_top_moves,
+            result.solve_rate() * 100.0,
+            result.average_moves(),
+            result.elapsed.as_secs_f64()
+        );
+        if result.heuristics == Heuristics::DEFAULT {
+            println!("{}", row.green());
+        } else {
+            println!("{}", row);
+        }
+    }
+    Ok(())
+}
+
+/// Optimize the solution if asked to, then save it and describe it, or write it as JSON
+///
+/// `details` are what the search found out besides the solution, for the JSON output
+fn finish_solution(
+    args: &Args,
+    board: Board,
+    moves_played: Vec<usize>,
+    details: ReportDetails,
+    verbosity: Verbosity,
+) -> Result<()> {
+    // Only a line that wins has an ending worth shortening
+    let optimized = (args.optimize && wins(&board, &moves_played)).then(|| {
+        let optimized = optimize_solution(&board, &moves_played, args.optimize_window, verbosity);
+        print_text(
+            args,
+            format!(
+                "Optimized from {} to {} moves made",
+                optimized.moves_before, optimized.moves_after
+            )
+            .green(),
+        );
+        optimized
+    });
+    let moves_played = match &optimized {
+        Some(optimized) => optimized.moves_played.clone(),
+        None => moves_played,
+    };
+    save_solution(args.save_solution.as_deref(), &board, &moves_played)?;
+    match args.format {
+        OutputFormat::Text => {
+            describe_solution(board, moves_played, verbosity);
+            if let Some(stats) = details.stats {
+                print_stats(args, stats);
+            }
+        }
+        OutputFormat::Json => {
+            let details = ReportDetails {
+                optimized: optimized.as_ref(),
+                ..details
             };
+            println!("{}", json_report(&board, &moves_played, details))
+        }
+    }
+    Ok(())
+}
 
-            for (moves_played, r#move) in moves.iter().enumerate() {
-                let (_, draws, _) = r#move;
+/// Whether playing the moves clears the board
+fn wins(board: &Board, moves_played: &[usize]) -> bool {
+    let mut board = board.clone();
+    for &move_num in moves_played {
+        board.play_move(board.get_moves()[move_num - 1]);
+    }
+    board.completed
+}
 
-                // Always play all no draw moves
-                if *draws > 0 && moves_played > max_moves {
-                    break;
-                }
-                if *draws + board.moves + 1 >= max_depth as i32 {
-                    break;
-                }
+/// Print the stats of the search if they were asked for, in the text output
+fn print_stats(args: &Args, stats: &SearchStats) {
+    if args.format == OutputFormat::Json {
+        return;
+    }
+    match args.stats {
+        Some(StatsFormat::Table) => {
+            println!();
+            print!("{}", stats);
+        }
+        Some(StatsFormat::Json) => println!("{}", serde_json::to_string(stats).unwrap()),
+        None => {}
+    }
+}
 
-                let mut new_board: Board = board.clone();
-                new_board.play_move(*r#move);
-                if verbosity >= Verbosity::Medium {
-                    *total_moves_played.lock().unwrap() += 1;
-                }
+/// Print a line of the text output, which is left out of the JSON output
+fn print_text(args: &Args, line: impl fmt::Display) {
+    if args.format == OutputFormat::Text {
+        println!("{}", line);
+    }
+}
 
-                let board_state = new_board.get_state();
-                let mut seen_states = seen_states.lock().unwrap();
+fn save_solution(path: Option<&Path>, board: &Board, moves_played: &[usize]) -> Result<()> {
+    if let Some(path) = path {
+        fs::write(path, write_solution(board, moves_played))
+            .with_context(|| format!("Couldn't write {}", path.display()))?;
+    }
+    Ok(())
+}
 
-                if seen_states.contains(&board_state) {
-                    if verbosity >= Verbosity::Medium {
-                        *duplicates.lock().unwrap() += 1;
-                    }
-                    continue;
-                }
-                seen_states.insert(board_state);
+fn describe_solution(mut board: Board, solution: Vec<usize>, verbosity: Verbosity) {
+    let mut moves_made: i32 = board.moves;
+    for move_num in solution.iter() {
+        if verbosity >= Verbosity::High {
+            pretty_print_board(&board);
+        }
 
-                let mut moves_made = moves_made.clone();
-                moves_made.push(moves_played + 1);
+        let moves = board.get_moves();
+        if moves.is_empty() {
+            panic!("No moves left?");
+        }
 
-                match queues.lock().unwrap().get_mut(new_board.moves as usize) {
-                    Some(sub_queue) => sub_queue.push((new_board, moves_made)),
-                    None => panic!("No queue for move count {}", new_board.moves),
-                };
+        if verbosity >= Verbosity::VeryHigh {
+            println!("Available moves:");
+            for (idx, r#move) in moves.iter().enumerate() {
+                if idx + 1 == *move_num {
+                    print!("> ");
+                }
+                pretty_print_move(&board, idx as i32 + 1, *r#move, true);
             }
-            None
-        });
-
-        if let Some(result) = result {
-            return Ok(result);
         }
 
-        queue_num += 1;
-    }
+        let r#move = match moves.get(*move_num - 1) {
+            Some(x) => x,
+            None => panic!("Move {} not found in moves: {:?}", move_num, moves),
+        };
 
-    println!(
-        "{}",
-        format!("No solution found with a max depth of {}", max_depth).red()
-    );
+        if verbosity < Verbosity::VeryHigh {
+            pretty_print_move(&board, moves_made, *r#move, true);
+        }
 
-    Ok(vec![])
+        board.play_move(*r#move);
+
+        moves_made += 1 + r#move.draws;
+    }
+    if board.completed {
+        println!("[{}] {}", board.moves, "All done!".green());
+    } else {
+        println!(
+            "[{}] {}",
+            board.moves,
+            format!("{} cards left", board.cards_left()).yellow()
+        );
+    }
 }
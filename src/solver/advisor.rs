@@ -0,0 +1,239 @@
+use crate::game::board::Board;
+use crate::game::card::RawCard;
+use crate::game::r#move::Move;
+use crate::game::utils::Verbosity;
+use crate::solver::bfs::with_config;
+use crate::solver::config::{AbortReason, Heuristics, Limits, SearchOutcome, SolverConfig};
+use crate::validators::missing_cards;
+use anyhow::{bail, Result};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::collections::BTreeMap;
+
+/// A deal where some of the stack cards are still face down
+#[derive(Debug, Clone)]
+pub struct PartialBoard {
+    board_cards: Vec<RawCard>,
+    stack: Vec<Option<RawCard>>,
+    missing: Vec<RawCard>,
+    clear_all: bool,
+}
+
+impl PartialBoard {
+    pub fn new(
+        board_cards: Vec<RawCard>,
+        stack: Vec<Option<RawCard>>,
+        clear_all: bool,
+    ) -> Result<PartialBoard> {
+        if board_cards.len() != 28 {
+            bail!("The board has {} cards, but needs 28", board_cards.len());
+        }
+        if stack.len() != 24 {
+            bail!("The stack has {} cards, but needs 24", stack.len());
+        }
+        let missing = missing_cards(&board_cards, &stack)?;
+
+        Ok(PartialBoard {
+            board_cards,
+            stack,
+            missing,
+            clear_all,
+        })
+    }
+
+    /// Deal the missing cards into the unknown stack positions in a random order
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> Board {
+        let mut missing = self.missing.clone();
+        missing.shuffle(rng);
+        let mut missing = missing.into_iter();
+
+        let stack: Vec<RawCard> = self
+            .stack
+            .iter()
+            .map(|card| card.unwrap_or_else(|| missing.next().unwrap()))
+            .collect();
+        let leaf_idxs: Vec<usize> = vec![21, 22, 23, 24, 25, 26, 27];
+
+        Board::new(self.board_cards.clone(), stack, leaf_idxs, self.clear_all)
+    }
+
+    fn is_known(&self, card: RawCard) -> bool {
+        self.board_cards.contains(&card) || self.stack.contains(&Some(card))
+    }
+}
+
+/// How a move did over the sampled stack orders
+#[derive(Debug, Clone)]
+pub struct MoveAdvice {
+    pub r#move: Move,
+    /// Number of samples where the move could be played
+    pub samples: usize,
+    /// Number of those samples where a solution was found after playing the move
+    pub wins: usize,
+}
+
+impl MoveAdvice {
+    pub fn win_rate(&self) -> f64 {
+        self.wins as f64 / self.samples as f64
+    }
+}
+
+/// Rank the next moves by how likely they are to lead to a win
+///
+/// Each sample deals the unknown stack cards in a random order consistent with the known cards,
+/// then searches for a solution after every move that only involves known cards. The search sees
+/// the whole sampled stack, so the win rates are somewhat optimistic, but they are comparable
+/// between moves. The best move comes first.
+///
+/// The searches run with the config's threads, move scorer and `deterministic` setting. The time
+/// limit is for the whole ranking, while the limits on states and memory hold for each search on
+/// its own. If a search hits one of them, the advice so far is returned along with the reason.
+pub fn advise_moves<R: Rng>(
+    partial: &PartialBoard,
+    samples: usize,
+    max_depth: usize,
+    heuristics: &Heuristics,
+    config: &SolverConfig,
+    rng: &mut R,
+) -> Result<(Vec<MoveAdvice>, Option<AbortReason>)> {
+    let boards: Vec<Board> = (0..samples).map(|_| partial.sample(rng)).collect();
+    let limits = Limits::new(config);
+
+    let (advice, aborted) = with_config(config, |search| {
+        let mut advice: BTreeMap<Move, MoveAdvice> = BTreeMap::new();
+
+        for board in boards {
+            for r#move in board.get_moves() {
+                let Move { left, right, .. } = r#move;
+                if !partial.is_known(left) || right.is_some_and(|right| !partial.is_known(right)) {
+                    continue;
+                }
+
+                let mut new_board = board.clone();
+                new_board.play_move(r#move);
+                let (outcome, _) = search(
+                    new_board,
+                    max_depth,
+                    *heuristics,
+                    config.move_scorer.as_ref(),
+                    &limits,
+                    Verbosity::Off,
+                );
+                let lost = match outcome {
+                    SearchOutcome::Done(solution) => solution.is_partial(),
+                    // The sample can't be counted without knowing how it ends
+                    SearchOutcome::Aborted { reason, .. } => return (advice, Some(reason)),
+                };
+
+                let entry = advice.entry(r#move).or_insert(MoveAdvice {
+                    r#move,
+                    samples: 0,
+                    wins: 0,
+                });
+                entry.samples += 1;
+                if !lost {
+                    entry.wins += 1;
+                }
+            }
+        }
+
+        (advice, None)
+    })?;
+
+    let mut advice: Vec<MoveAdvice> = advice.into_values().collect();
+    // Stable sort, so ties keep the move order
+    advice.sort_by(|a, b| {
+        b.win_rate()
+            .total_cmp(&a.win_rate())
+            .then(b.samples.cmp(&a.samples))
+    });
+
+    Ok((advice, aborted))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::game::card::Card;
+    use crate::game::utils::parse_partial_board;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_partial_board_sample() {
+        let (board_cards, stack) = parse_partial_board(
+            "jj6j88a95k3ka02j4q32k0767qk7".to_string(),
+            "68480a55q69a2339527q44??".to_string(),
+        )
+        .unwrap();
+        let partial = PartialBoard::new(board_cards, stack.clone(), false).unwrap();
+
+        let mut rng = rand::thread_rng();
+        for _ in 0..10 {
+            let board = partial.sample(&mut rng);
+
+            // The known cards stay where they are
+            for (known, sampled) in stack.iter().zip(board.stack.iter()) {
+                if let Some(known) = known {
+                    assert_eq!(known, sampled);
+                }
+            }
+
+            // And the missing 9 and 10 fill in the rest
+            let mut unknown: Vec<Card> =
+                board.stack[22..].iter().map(|&card| card.into()).collect();
+            unknown.sort();
+            assert_eq!(unknown, vec![Card(9), Card(10)]);
+        }
+    }
+
+    #[test]
+    fn test_advise_moves_stops_at_limits() {
+        let (board_cards, stack) = parse_partial_board(
+            "jj6j88a95k3ka02j4q32k0767qk7".to_string(),
+            "68480a55q69a2339527q44??".to_string(),
+        )
+        .unwrap();
+        let partial = PartialBoard::new(board_cards, stack, false).unwrap();
+        let config = SolverConfig {
+            max_states: Some(100),
+            deterministic: true,
+            ..SolverConfig::default()
+        };
+
+        let (advice, aborted) = advise_moves(
+            &partial,
+            4,
+            60,
+            &Heuristics::DEFAULT,
+            &config,
+            &mut StdRng::seed_from_u64(0),
+        )
+        .unwrap();
+
+        assert_eq!(aborted, Some(AbortReason::MaxStates));
+        assert!(advice.is_empty());
+    }
+
+    #[test]
+    fn test_partial_board_too_many_unknown() {
+        let (board_cards, stack) = parse_partial_board(
+            "jj6j88a95k3ka02j4q32k0767qk7".to_string(),
+            "68480a55q69a2339527q4????".to_string(),
+        )
+        .unwrap();
+
+        assert!(PartialBoard::new(board_cards, stack, false).is_err());
+    }
+
+    #[test]
+    fn test_partial_board_wrong_length() {
+        let (board_cards, stack) = parse_partial_board(
+            "jj6j88a95k3ka02j4q32k0767qk".to_string(),
+            "68480a55q69a2339527q449?7".to_string(),
+        )
+        .unwrap();
+
+        assert!(PartialBoard::new(board_cards, stack, false).is_err());
+    }
+}
@@ -0,0 +1,211 @@
+use crate::game::board::Board;
+use crate::game::r#move::Move;
+use crate::game::utils::Verbosity;
+use crate::solver::bfs::{estimated_bytes, Solution};
+use crate::solver::evaluate::Evaluator;
+use crate::solver::stats::SearchStats;
+use rayon::prelude::*;
+use std::cmp::Reverse;
+use std::collections::HashSet;
+use std::time::Instant;
+
+/// Search for a solution, keeping only the best boards for each move count
+///
+/// Every move is played from the boards that are kept, but only the `beam_width` boards the
+/// evaluator scores highest are kept for each move count, counting the boards reached by removing
+/// kings without a move. Returns the first solution found, or the line that leaves the fewest
+/// cards, with the stats of the search. Boards that fall outside the beam count as pruned.
+pub fn beam_search(
+    board: Board,
+    max_depth: usize,
+    beam_width: usize,
+    evaluator: &dyn Evaluator,
+    verbosity: Verbosity,
+) -> (Solution, SearchStats) {
+    let mut seen_states: HashSet<Vec<u8>> = HashSet::new();
+    let mut best_partial = Solution::from_board(&board, vec![]);
+    let mut best_moves = i32::MAX;
+
+    let mut queues: Vec<Vec<(Board, Vec<usize>)>> = vec![vec![]; max_depth];
+    seen_states.insert(board.get_state());
+    queues[0].push((board, vec![]));
+
+    let start = Instant::now();
+    let mut stats = SearchStats::default();
+
+    for queue_num in 0..max_depth {
+        let mut queue = std::mem::take(&mut queues[queue_num]);
+        if queue.is_empty() {
+            continue;
+        }
+
+        // Removing a king from the left of the stack doesn't add to the move count, so the boards
+        // it leads to are added to the queue first, and the beam is applied to all of them at once
+        let mut unplayed = 0;
+        while unplayed < queue.len() {
+            let children = play_moves(&queue[unplayed..], max_depth, |r#move| r#move.draws < 0);
+            unplayed = queue.len();
+            for (new_board, moves_made) in children {
+                if keep_child(&new_board, &mut seen_states, &mut stats, &mut best_moves) {
+                    queue.push((new_board, moves_made));
+                }
+            }
+        }
+        let queue_size = queue.len();
+
+        // Highest score first, with ties going to the lowest move sequence so the beam is the
+        // same from run to run
+        queue.sort_by_cached_key(|(board, moves_made)| {
+            (Reverse(evaluator.evaluate(board)), moves_made.clone())
+        });
+        queue.truncate(beam_width);
+        stats.pruned += (queue_size - queue.len()) as u64;
+        stats.record_frontier(queue_size, estimated_bytes(seen_states.len()));
+
+        if verbosity >= Verbosity::Low {
+            println!(
+                "Queue {} - size: {} - kept: {}",
+                queue_num,
+                queue_size,
+                queue.len()
+            );
+        }
+
+        if let Some((board, moves_made)) = queue.iter().find(|(board, _)| board.completed) {
+            stats.elapsed = start.elapsed();
+            return (Solution::from_board(board, moves_made.clone()), stats);
+        }
+        stats.add_expanded(queue_num, queue.len() as u64);
+
+        for (board, moves_made) in queue.iter() {
+            let line = Solution::from_board(board, moves_made.clone());
+            if line.is_better_than(&best_partial) {
+                best_partial = line;
+            }
+        }
+
+        for (new_board, moves_made) in play_moves(&queue, max_depth, |r#move| r#move.draws >= 0) {
+            if keep_child(&new_board, &mut seen_states, &mut stats, &mut best_moves) {
+                queues[new_board.moves as usize].push((new_board, moves_made));
+            }
+        }
+    }
+
+    stats.elapsed = start.elapsed();
+    (best_partial, stats)
+}
+
+/// Play the moves `play` picks from every board, along with the moves that led to them
+fn play_moves(
+    boards: &[(Board, Vec<usize>)],
+    max_depth: usize,
+    play: impl Fn(&Move) -> bool + Sync,
+) -> Vec<(Board, Vec<usize>)> {
+    boards
+        .par_iter()
+        .flat_map_iter(|(board, moves_made)| {
+            board
+                .get_moves()
+                .into_iter()
+                .enumerate()
+                .take_while(|(_, r#move)| r#move.draws + board.moves + 1 < max_depth as i32)
+                .filter(|(_, r#move)| play(r#move))
+                .map(|(idx, r#move)| {
+                    let mut new_board = board.clone();
+                    new_board.play_move(r#move);
+                    let mut moves_made = moves_made.clone();
+                    moves_made.push(idx + 1);
+                    (new_board, moves_made)
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Whether a board reached by a move should be searched further, counting it in the stats
+fn keep_child(
+    new_board: &Board,
+    seen_states: &mut HashSet<Vec<u8>>,
+    stats: &mut SearchStats,
+    best_moves: &mut i32,
+) -> bool {
+    stats.moves_played += 1;
+    if new_board.completed {
+        *best_moves = (*best_moves).min(new_board.moves);
+    } else if new_board.is_dead() {
+        stats.dead_ends += 1;
+        return false;
+    } else if new_board.moves + new_board.lower_bound_moves() >= *best_moves {
+        stats.over_bound += 1;
+        return false;
+    }
+    if seen_states.insert(new_board.get_state()) {
+        true
+    } else {
+        stats.duplicates += 1;
+        false
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::game::card::RawCard;
+    use crate::solver::evaluate::{CardsCleared, Weighted};
+    use crate::test_utils::readme_board;
+
+    #[test]
+    fn test_beam_search_solves() {
+        let (solution, stats) = beam_search(
+            readme_board(),
+            60,
+            200,
+            &Weighted::default(),
+            Verbosity::Off,
+        );
+
+        assert!(!solution.is_partial());
+        assert_eq!(solution.cards_left, 0);
+        assert!(stats.nodes_expanded() > 0);
+        assert!(stats.peak_frontier > 0);
+    }
+
+    #[test]
+    fn test_beam_search_narrow_beam_is_partial() {
+        let (solution, stats) = beam_search(readme_board(), 15, 1, &CardsCleared, Verbosity::Off);
+
+        assert!(solution.is_partial());
+        assert!(solution.moves < 15);
+        assert!(stats.pruned > 0);
+        assert!(stats.expanded.iter().all(|&boards| boards <= 1));
+    }
+
+    #[test]
+    fn test_beam_search_plays_free_kings() {
+        // Only the ace at the top left, with the king left of the stack pointer covering the
+        // queen it needs. Removing the king is free, after which the queen matches without a draw.
+        let mut board_cards: Vec<Option<RawCard>> = vec![None; 28];
+        board_cards[0] = Some(RawCard(0));
+        let board = Board::from_position(&board_cards, vec![RawCard(11), RawCard(12)], 2, 0, false);
+
+        let (solution, _) = beam_search(board, 10, 10, &CardsCleared, Verbosity::Off);
+
+        assert!(!solution.is_partial());
+        assert_eq!(solution.moves, 1);
+        assert_eq!(solution.moves_played.len(), 2);
+    }
+
+    #[test]
+    fn test_beam_search_applies_the_beam_once_per_move_count() {
+        // The king left of the stack pointer can be removed without a move, so the board it leads
+        // to shares the first move count with the deal, and only one of the two is kept
+        let mut board_cards: Vec<Option<RawCard>> = vec![None; 28];
+        board_cards[0] = Some(RawCard(0));
+        let board = Board::from_position(&board_cards, vec![RawCard(11), RawCard(12)], 2, 0, false);
+
+        let (_, stats) = beam_search(board, 10, 1, &CardsCleared, Verbosity::Off);
+
+        assert_eq!(stats.expanded[0], 1);
+        assert_eq!(stats.pruned, 1);
+    }
+}
@@ -0,0 +1,623 @@
+use crate::game::board::Board;
+use crate::game::r#move::Move;
+use crate::game::utils::Verbosity;
+use crate::solver::config::{AbortReason, Heuristics, Limits, SearchOutcome, SolverConfig};
+use crate::solver::learned::MoveScorer;
+use crate::solver::stats::SearchStats;
+use anyhow::Result;
+use rayon::prelude::*;
+use rayon::ThreadPoolBuilder;
+use std::collections::HashSet;
+use std::mem::size_of;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// A line of play found by the search
+///
+/// Serialized as `moves`, `moves_played` and `cards_left`
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
+pub struct Solution {
+    /// Moves made, including draws
+    pub moves: i32,
+    /// The moves played, as 1-based indexes into `Board::get_moves`
+    pub moves_played: Vec<usize>,
+    /// Cards still left to clear at the end of the line
+    pub cards_left: usize,
+}
+
+impl Solution {
+    /// The line doesn't clear the board, it's just the best one found before the search stopped
+    pub fn is_partial(&self) -> bool {
+        self.cards_left > 0
+    }
+
+    pub(crate) fn from_board(board: &Board, moves_played: Vec<usize>) -> Solution {
+        Solution {
+            moves: board.moves,
+            moves_played,
+            cards_left: board.cards_left(),
+        }
+    }
+
+    /// Fewer cards left is better, and then fewer moves made
+    pub(crate) fn is_better_than(&self, other: &Solution) -> bool {
+        (self.cards_left, self.moves) < (other.cards_left, other.moves)
+    }
+}
+
+/// Search for a solution, returning the best partial line if none is found
+///
+/// The config sets the threads to search with and when to give up, in which case the best line
+/// found so far is returned with the reason. With `deterministic` set, the same solution is
+/// returned on every run, however many threads there are, see `search_in_order`. The stats of the
+/// search are returned along with the outcome.
+pub fn simulate_games(
+    board: Board,
+    max_depth: usize,
+    heuristics: &Heuristics,
+    config: &SolverConfig,
+    verbosity: Verbosity,
+) -> Result<(SearchOutcome, SearchStats)> {
+    with_config(config, |search| {
+        search(
+            board,
+            max_depth,
+            *heuristics,
+            config.move_scorer.as_ref(),
+            &Limits::new(config),
+            verbosity,
+        )
+    })
+}
+
+/// `search` or `search_in_order`
+pub(crate) type SearchFn = fn(
+    Board,
+    usize,
+    Heuristics,
+    Option<&MoveScorer>,
+    &Limits,
+    Verbosity,
+) -> (SearchOutcome, SearchStats);
+
+/// Run searches on the threads the config sets, with `search_in_order` if it has to be
+/// deterministic
+pub(crate) fn with_config<T: Send>(
+    config: &SolverConfig,
+    run: impl FnOnce(SearchFn) -> T + Send,
+) -> Result<T> {
+    let pool = match config.threads {
+        Some(threads) => Some(ThreadPoolBuilder::new().num_threads(threads).build()?),
+        None => None,
+    };
+    let search: SearchFn = if config.deterministic {
+        search_in_order
+    } else {
+        search
+    };
+
+    Ok(match pool {
+        Some(pool) => pool.install(|| run(search)),
+        None => run(search),
+    })
+}
+
+/// Rough number of bytes a stored state takes up: its key in the seen states, and the board
+/// waiting in a queue with the moves that led to it
+pub(crate) fn estimated_bytes(states: usize) -> usize {
+    states * (size_of::<(Board, Vec<usize>)>() + 512)
+}
+
+/// Run the search without reporting the result
+///
+/// Returns the first solution found, or the line that leaves the fewest cards if the search runs
+/// out of moves first or hits one of the limits
+pub(crate) fn search(
+    board: Board,
+    max_depth: usize,
+    heuristics: Heuristics,
+    scorer: Option<&MoveScorer>,
+    limits: &Limits,
+    verbosity: Verbosity,
+) -> (SearchOutcome, SearchStats) {
+    let seen_states = Arc::new(Mutex::new(HashSet::new()));
+    let best_partial = Arc::new(Mutex::new(Solution::from_board(&board, vec![])));
+
+    // Pre-create 60 queues for different move counts
+    let queues = Arc::new(Mutex::new(vec![vec![]; max_depth]));
+
+    // Initial board at 0 moves
+    queues
+        .lock()
+        .unwrap()
+        .get_mut(0)
+        .unwrap()
+        .push((board, vec![]));
+
+    let start = Instant::now();
+    let mut stats = SearchStats::default();
+
+    let layer_expanded = AtomicU64::new(0);
+    let moves_played_count = AtomicU64::new(0);
+    let duplicates = AtomicU64::new(0);
+    let dead_ends = AtomicU64::new(0);
+    let over_bound = AtomicU64::new(0);
+    let pruned = AtomicU64::new(0);
+    // Boards in the queues, which count towards the limits along with the seen states, the same
+    // as in `search_in_order`
+    let waiting = AtomicUsize::new(1);
+
+    // Fewest moves of any solution found so far, boards that can't beat it aren't worth playing.
+    // Until there is one, boards that can't finish within the max depth are still played, as they
+    // can make for the best partial line.
+    let best_moves = Arc::new(Mutex::new(i32::MAX));
+    let abort: Mutex<Option<AbortReason>> = Mutex::new(None);
+
+    let stored_states = || seen_states.lock().unwrap().len() + waiting.load(Ordering::Relaxed);
+    let check_limits = || {
+        let states = stored_states();
+        let exceeded = limits.exceeded(states, estimated_bytes(states));
+        let mut abort = abort.lock().unwrap();
+        *abort = abort.or(exceeded);
+        *abort
+    };
+    let finish = |stats: SearchStats| SearchStats {
+        moves_played: moves_played_count.load(Ordering::Relaxed),
+        duplicates: duplicates.load(Ordering::Relaxed),
+        dead_ends: dead_ends.load(Ordering::Relaxed),
+        over_bound: over_bound.load(Ordering::Relaxed),
+        pruned: pruned.load(Ordering::Relaxed),
+        elapsed: start.elapsed(),
+        ..stats
+    };
+
+    for queue_num in 0..max_depth {
+        // Removing a king from the left of the stack doesn't add to the move count, so boards can
+        // keep getting added to the queue being processed
+        loop {
+            if let Some(reason) = check_limits() {
+                let best = best_partial.lock().unwrap().clone();
+                return (SearchOutcome::Aborted { reason, best }, finish(stats));
+            }
+
+            let states = stored_states();
+            let mut queue = std::mem::take(&mut queues.lock().unwrap()[queue_num]);
+            if queue.is_empty() {
+                break;
+            }
+            let queue_size = queue.len();
+            waiting.fetch_sub(queue_size, Ordering::Relaxed);
+            stats.record_frontier(queue_size, estimated_bytes(states));
+
+            if verbosity == Verbosity::Low {
+                println!("Queue {} - size: {}", queue_num, queue_size,);
+            } else if verbosity >= Verbosity::Medium {
+                println!(
+                    "Queue {} - size: {} - total moves played: {} - duplicates: {} - dead ends: {} - over bound: {}",
+                    queue_num,
+                    queue_size,
+                    moves_played_count.load(Ordering::Relaxed),
+                    duplicates.load(Ordering::Relaxed),
+                    dead_ends.load(Ordering::Relaxed),
+                    over_bound.load(Ordering::Relaxed)
+                );
+            }
+
+            layer_expanded.store(0, Ordering::Relaxed);
+            let result = queue.par_drain(..).find_map_any(|(board, moves_made)| {
+                if board.completed {
+                    return Some(Solution::from_board(&board, moves_made.clone()));
+                }
+                if check_limits().is_some() {
+                    return None;
+                }
+                layer_expanded.fetch_add(1, Ordering::Relaxed);
+
+                {
+                    let mut best_partial = best_partial.lock().unwrap();
+                    let line = Solution::from_board(&board, vec![]);
+                    if line.is_better_than(&best_partial) {
+                        *best_partial = Solution {
+                            moves_played: moves_made.clone(),
+                            ..line
+                        };
+                    }
+                }
+
+                let (moves, cut) = moves_to_try(&board, max_depth, heuristics, scorer);
+                pruned.fetch_add(cut, Ordering::Relaxed);
+                for (moves_played, r#move) in moves {
+                    let mut new_board: Board = board.clone();
+                    new_board.play_move(r#move);
+                    moves_played_count.fetch_add(1, Ordering::Relaxed);
+
+                    let board_state = new_board.get_state();
+                    let mut seen_states = seen_states.lock().unwrap();
+
+                    if seen_states.contains(&board_state) {
+                        duplicates.fetch_add(1, Ordering::Relaxed);
+                        continue;
+                    }
+                    seen_states.insert(board_state);
+                    drop(seen_states);
+
+                    if new_board.is_dead() {
+                        dead_ends.fetch_add(1, Ordering::Relaxed);
+                        continue;
+                    }
+
+                    if new_board.completed {
+                        let mut best_moves = best_moves.lock().unwrap();
+                        *best_moves = (*best_moves).min(new_board.moves);
+                    } else if new_board.moves + new_board.lower_bound_moves()
+                        >= *best_moves.lock().unwrap()
+                    {
+                        over_bound.fetch_add(1, Ordering::Relaxed);
+                        continue;
+                    }
+
+                    let mut moves_made = moves_made.clone();
+                    moves_made.push(moves_played + 1);
+
+                    match queues.lock().unwrap().get_mut(new_board.moves as usize) {
+                        Some(sub_queue) => sub_queue.push((new_board, moves_made)),
+                        None => panic!("No queue for move count {}", new_board.moves),
+                    };
+                    waiting.fetch_add(1, Ordering::Relaxed);
+                }
+                None
+            });
+            stats.add_expanded(queue_num, layer_expanded.load(Ordering::Relaxed));
+
+            if let Some(result) = result {
+                return (SearchOutcome::Done(result), finish(stats));
+            }
+        }
+    }
+
+    let best_partial = best_partial.lock().unwrap().clone();
+    (SearchOutcome::Done(best_partial), finish(stats))
+}
+
+/// The moves the search plays from a board, with their index into `Board::get_moves`, and how
+/// many moves were left out by the heuristics
+///
+/// Moves are sorted first by draws and then by the card being removed, or by their score if
+/// there's a scorer. Moves that need no draws are always played, and of the others only the first
+/// few.
+fn moves_to_try(
+    board: &Board,
+    max_depth: usize,
+    heuristics: Heuristics,
+    scorer: Option<&MoveScorer>,
+) -> (Vec<(usize, Move)>, u64) {
+    let max_moves = if board.moves as usize <= heuristics.first_games {
+        heuristics.first_top_moves
+    } else {
+        heuristics.top_moves
+    };
+
+    let mut moves: Vec<(usize, Move)> = board.get_moves().into_iter().enumerate().collect();
+    if let Some(scorer) = scorer {
+        scorer.sort(board, &mut moves);
+    }
+
+    let mut pruned = 0;
+    let moves = moves
+        .into_iter()
+        .enumerate()
+        .filter(|&(_, (_, Move { draws, .. }))| draws + board.moves + 1 < max_depth as i32)
+        .filter(|&(order, (_, Move { draws, .. }))| {
+            let keep = draws == 0 || order <= max_moves;
+            pruned += u64::from(!keep);
+            keep
+        })
+        .map(|(_, r#move)| r#move)
+        .collect();
+    (moves, pruned)
+}
+
+/// A board played from the queue, whether it's dead, and the fewest moves it could finish in
+type Child = (Board, Vec<usize>, bool, i32);
+
+/// Like `search`, but with results that don't depend on the order threads finish in
+///
+/// Boards are only deduplicated once their move count comes up, keeping the one with the lowest
+/// move sequence, and of the completed boards the one with the lowest move sequence is returned.
+/// Moves are still played in parallel, but their results are gathered in order. This keeps more
+/// boards in memory than `search` does.
+pub(crate) fn search_in_order(
+    board: Board,
+    max_depth: usize,
+    heuristics: Heuristics,
+    scorer: Option<&MoveScorer>,
+    limits: &Limits,
+    verbosity: Verbosity,
+) -> (SearchOutcome, SearchStats) {
+    let mut seen_states: HashSet<Vec<u8>> = HashSet::new();
+    let mut best_partial = Solution::from_board(&board, vec![]);
+    let mut best_moves = i32::MAX;
+
+    let mut queues: Vec<Vec<(Board, Vec<usize>)>> = vec![vec![]; max_depth];
+    queues[0].push((board, vec![]));
+
+    let start = Instant::now();
+    let mut stats = SearchStats::default();
+
+    for queue_num in 0..max_depth {
+        // Removing a king from the left of the stack doesn't add to the move count, so boards can
+        // keep getting added to the queue being processed
+        loop {
+            let states = seen_states.len() + queues.iter().map(Vec::len).sum::<usize>();
+            if let Some(reason) = limits.exceeded(states, estimated_bytes(states)) {
+                stats.elapsed = start.elapsed();
+                let outcome = SearchOutcome::Aborted {
+                    reason,
+                    best: best_partial,
+                };
+                return (outcome, stats);
+            }
+
+            let mut queue = std::mem::take(&mut queues[queue_num]);
+            if queue.is_empty() {
+                break;
+            }
+            let queue_size = queue.len();
+            queue.sort_by(|(_, a), (_, b)| a.cmp(b));
+            queue.retain(|(board, _)| seen_states.insert(board.get_state()));
+            stats.duplicates += (queue_size - queue.len()) as u64;
+            stats.record_frontier(queue_size, estimated_bytes(states));
+
+            if verbosity == Verbosity::Low {
+                println!("Queue {} - size: {}", queue_num, queue.len());
+            } else if verbosity >= Verbosity::Medium {
+                println!(
+                    "Queue {} - size: {} - duplicates: {} - dead ends: {} - over bound: {}",
+                    queue_num,
+                    queue.len(),
+                    stats.duplicates,
+                    stats.dead_ends,
+                    stats.over_bound
+                );
+            }
+
+            if let Some((board, moves_made)) = queue.iter().find(|(board, _)| board.completed) {
+                stats.elapsed = start.elapsed();
+                let solution = Solution::from_board(board, moves_made.clone());
+                return (SearchOutcome::Done(solution), stats);
+            }
+            stats.add_expanded(queue_num, queue.len() as u64);
+
+            for (board, moves_made) in queue.iter() {
+                let line = Solution::from_board(board, vec![]);
+                if line.is_better_than(&best_partial) {
+                    best_partial = Solution {
+                        moves_played: moves_made.clone(),
+                        ..line
+                    };
+                }
+            }
+
+            let children: Vec<(Vec<Child>, u64)> = queue
+                .par_iter()
+                .map(|(board, moves_made)| {
+                    let (moves, cut) = moves_to_try(board, max_depth, heuristics, scorer);
+                    let children = moves
+                        .into_iter()
+                        .map(|(moves_played, r#move)| {
+                            let mut new_board = board.clone();
+                            new_board.play_move(r#move);
+                            let mut moves_made = moves_made.clone();
+                            moves_made.push(moves_played + 1);
+
+                            let dead = new_board.is_dead();
+                            let needed = new_board.moves + new_board.lower_bound_moves();
+                            (new_board, moves_made, dead, needed)
+                        })
+                        .collect();
+                    (children, cut)
+                })
+                .collect();
+
+            stats.pruned += children.iter().map(|(_, cut)| cut).sum::<u64>();
+            let children = children.into_iter().flat_map(|(children, _)| children);
+            for (new_board, moves_made, dead, needed) in children {
+                stats.moves_played += 1;
+                if dead {
+                    stats.dead_ends += 1;
+                    continue;
+                }
+                if new_board.completed {
+                    best_moves = best_moves.min(new_board.moves);
+                } else if needed >= best_moves {
+                    stats.over_bound += 1;
+                    continue;
+                }
+                queues[new_board.moves as usize].push((new_board, moves_made));
+            }
+        }
+    }
+
+    stats.elapsed = start.elapsed();
+    (SearchOutcome::Done(best_partial), stats)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::game::card::RawCard;
+    use crate::solver::config::CancellationToken;
+    use crate::test_utils::{readme_board, solve};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_search_returns_best_partial_line() {
+        // Nowhere near enough moves to clear the board
+        let solution = search(
+            readme_board(),
+            10,
+            Heuristics::DEFAULT,
+            None,
+            &Limits::default(),
+            Verbosity::Off,
+        )
+        .0
+        .into_solution();
+
+        assert!(solution.is_partial());
+        assert!(solution.cards_left < 28);
+        assert!(solution.moves < 10);
+        assert!(!solution.moves_played.is_empty());
+    }
+
+    #[test]
+    fn test_lower_bound_moves_along_solution() {
+        let solution = solve(&readme_board());
+        assert!(!solution.is_partial());
+
+        // The bound never goes over the moves the solution actually needed from there
+        let mut board = readme_board();
+        for move_num in solution.moves_played.iter() {
+            assert!(board.moves + board.lower_bound_moves() <= solution.moves);
+            let r#move = board.get_moves()[*move_num - 1];
+            board.play_move(r#move);
+        }
+        assert_eq!(board.lower_bound_moves(), 0);
+    }
+
+    #[test]
+    fn test_search_in_order_is_the_same_on_any_thread_count() {
+        let search_with_threads = |threads: usize| {
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap()
+                .install(|| {
+                    let (outcome, stats) = search_in_order(
+                        readme_board(),
+                        60,
+                        Heuristics::DEFAULT,
+                        None,
+                        &Limits::default(),
+                        Verbosity::Off,
+                    );
+                    // Only the time taken can differ
+                    let stats = SearchStats {
+                        elapsed: Duration::ZERO,
+                        ..stats
+                    };
+                    (outcome, stats)
+                })
+        };
+
+        let (outcome, stats) = search_with_threads(1);
+        assert!(!outcome.solution().is_partial());
+        assert!(stats.nodes_expanded() > 0);
+        assert!(stats.moves_played > stats.nodes_expanded());
+        assert_eq!(search_with_threads(4), (outcome, stats));
+    }
+
+    #[test]
+    fn test_search_plays_free_king_removals() {
+        // Only a queen left on the board, with a king left of the stack pointer that can be
+        // removed without using up a move, and an ace to match the queen with
+        let mut cards = vec![None; 28];
+        cards[0] = Some(RawCard(11));
+        let board = Board::from_position(&cards, vec![RawCard(12), RawCard(0)], 1, 0, true);
+
+        for search in [search as SearchFn, search_in_order] {
+            let (outcome, _) = search(
+                board.clone(),
+                10,
+                Heuristics::DEFAULT,
+                None,
+                &Limits::default(),
+                Verbosity::Off,
+            );
+            let solution = outcome.into_solution();
+            assert!(!solution.is_partial());
+            assert_eq!(solution.moves, 1);
+        }
+    }
+
+    #[test]
+    fn test_search_out_of_time() {
+        let limits = Limits {
+            deadline: Some(Instant::now()),
+            ..Limits::default()
+        };
+        let (outcome, _) = search(
+            readme_board(),
+            60,
+            Heuristics::DEFAULT,
+            None,
+            &limits,
+            Verbosity::Off,
+        );
+
+        assert_eq!(
+            outcome,
+            SearchOutcome::Aborted {
+                reason: AbortReason::TimeLimit,
+                best: Solution::from_board(&readme_board(), vec![]),
+            }
+        );
+    }
+
+    #[test]
+    fn test_search_max_states() {
+        let limits = Limits {
+            max_states: Some(100),
+            ..Limits::default()
+        };
+
+        for search in [search, search_in_order] {
+            match search(
+                readme_board(),
+                60,
+                Heuristics::DEFAULT,
+                None,
+                &limits,
+                Verbosity::Off,
+            )
+            .0
+            {
+                SearchOutcome::Aborted { reason, best } => {
+                    assert_eq!(reason, AbortReason::MaxStates);
+                    assert!(!best.moves_played.is_empty());
+                }
+                outcome => panic!("Search wasn't aborted: {:?}", outcome),
+            }
+        }
+    }
+
+    #[test]
+    fn test_simulate_games_cancelled() {
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let config = SolverConfig {
+            threads: Some(2),
+            cancel: Some(cancel),
+            ..SolverConfig::default()
+        };
+
+        let (outcome, _) = simulate_games(
+            readme_board(),
+            60,
+            &Heuristics::DEFAULT,
+            &config,
+            Verbosity::Off,
+        )
+        .unwrap();
+
+        assert!(matches!(
+            outcome,
+            SearchOutcome::Aborted {
+                reason: AbortReason::Cancelled,
+                ..
+            }
+        ));
+    }
+}
@@ -0,0 +1,345 @@
+use crate::game::board::Board;
+use crate::game::card::{MatchType, RawCard};
+use crate::game::r#move::Move;
+use crate::solver::external::{read_bytes, write_bytes};
+use crate::solver::optimal::SearchState;
+use crate::solver::stats::SearchStats;
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const MAGIC: &[u8] = b"pyrasol checkpoint 4\n";
+
+/// Where and how often the search saves its progress
+#[derive(Debug, Clone)]
+pub struct Checkpoints {
+    /// File the search state is written to
+    pub path: PathBuf,
+    /// Least time between two checkpoints, they are only written between queues
+    pub interval: Duration,
+    /// A checkpoint to carry on from instead of starting over
+    pub resume: Option<PathBuf>,
+}
+
+/// Write the search state to a file
+///
+/// The state is written next to the file first and then moved over it, so an interrupted write
+/// leaves the previous checkpoint in place
+pub(crate) fn save(
+    path: &Path,
+    board: &Board,
+    max_depth: usize,
+    state: &SearchState,
+) -> Result<()> {
+    let temp_path = path.with_extension("partial");
+    let file = File::create(&temp_path)
+        .with_context(|| format!("Couldn't create {}", temp_path.display()))?;
+    let mut writer = BufWriter::new(file);
+
+    writer.write_all(MAGIC)?;
+    write_bytes(&mut writer, &board.snapshot())?;
+    writer.write_all(&board.board_cards.map(|card| card.0))?;
+    write_u64(&mut writer, max_depth as u64)?;
+    writer.write_all(&[board.clears_all() as u8])?;
+
+    write_u64(&mut writer, state.queue_num as u64)?;
+    write_u64(&mut writer, state.best_moves as u64)?;
+    write_stats(&mut writer, &state.stats)?;
+
+    write_u64(&mut writer, state.queues.len() as u64)?;
+    for queue in state.queues.iter() {
+        write_u64(&mut writer, queue.len() as u64)?;
+        for board in queue {
+            write_bytes(&mut writer, &board.snapshot())?;
+        }
+    }
+
+    write_u64(&mut writer, state.parents.len() as u64)?;
+    for (child, edges) in state.parents.iter() {
+        write_bytes(&mut writer, child)?;
+        write_u64(&mut writer, edges.len() as u64)?;
+        for (parent, move_num, r#move) in edges {
+            write_bytes(&mut writer, parent)?;
+            write_u64(&mut writer, *move_num as u64)?;
+            write_move(&mut writer, r#move)?;
+        }
+    }
+
+    writer.flush()?;
+    drop(writer);
+    fs::rename(&temp_path, path)
+        .with_context(|| format!("Couldn't move the checkpoint to {}", path.display()))?;
+    Ok(())
+}
+
+/// Read the search state back from a file written by `save`
+///
+/// The board, max depth and whether the stack is cleared too have to be the same as the search
+/// that wrote it
+pub(crate) fn load(path: &Path, board: &Board, max_depth: usize) -> Result<SearchState> {
+    let file = File::open(path).with_context(|| format!("Couldn't open {}", path.display()))?;
+    let mut reader = BufReader::new(file);
+    read_state(&mut reader, board, max_depth)
+        .with_context(|| format!("Couldn't resume from {}", path.display()))
+}
+
+fn read_state(reader: &mut impl Read, board: &Board, max_depth: usize) -> Result<SearchState> {
+    let mut magic = vec![0u8; MAGIC.len()];
+    reader.read_exact(&mut magic)?;
+    if magic != MAGIC {
+        bail!("Not a checkpoint file");
+    }
+
+    let start = read_some_bytes(reader)?;
+    let mut board_cards = [0u8; 28];
+    reader.read_exact(&mut board_cards)?;
+    if start != board.snapshot() || board_cards != board.board_cards.map(|card| card.0) {
+        bail!("The checkpoint is for a different deal");
+    }
+    let checkpoint_depth = read_u64(reader)? as usize;
+    if checkpoint_depth != max_depth {
+        bail!(
+            "The checkpoint is for a max depth of {}, not {}",
+            checkpoint_depth,
+            max_depth
+        );
+    }
+    let mut clear_all = [0u8];
+    reader.read_exact(&mut clear_all)?;
+    if (clear_all[0] == 1) != board.clears_all() {
+        let cleared = |clear_all| match clear_all {
+            true => "the board and the stack",
+            false => "the board",
+        };
+        bail!(
+            "The checkpoint is for clearing {}, not {}",
+            cleared(clear_all[0] == 1),
+            cleared(board.clears_all())
+        );
+    }
+
+    let queue_num = read_u64(reader)? as usize;
+    let best_moves = read_u64(reader)? as i32;
+    let stats = read_stats(reader)?;
+
+    let mut queues = vec![];
+    for _ in 0..read_u64(reader)? {
+        let mut queue = vec![];
+        for _ in 0..read_u64(reader)? {
+            queue.push(board.restore_snapshot(&read_some_bytes(reader)?));
+        }
+        queues.push(queue);
+    }
+
+    let mut parents = HashMap::new();
+    for _ in 0..read_u64(reader)? {
+        let child = read_some_bytes(reader)?;
+        let mut edges = vec![];
+        for _ in 0..read_u64(reader)? {
+            let parent = read_some_bytes(reader)?;
+            let move_num = read_u64(reader)? as usize;
+            edges.push((parent, move_num, read_move(reader)?));
+        }
+        parents.insert(child, edges);
+    }
+
+    Ok(SearchState {
+        queue_num,
+        queues,
+        parents,
+        stats,
+        best_moves,
+    })
+}
+
+fn write_stats(writer: &mut impl Write, stats: &SearchStats) -> io::Result<()> {
+    write_u64(writer, stats.expanded.len() as u64)?;
+    for boards in stats.expanded.iter() {
+        write_u64(writer, *boards)?;
+    }
+    for value in [
+        stats.moves_played,
+        stats.duplicates,
+        stats.dead_ends,
+        stats.over_bound,
+        stats.pruned,
+        stats.peak_frontier as u64,
+        stats.peak_bytes as u64,
+        stats.elapsed.as_nanos() as u64,
+    ] {
+        write_u64(writer, value)?;
+    }
+    Ok(())
+}
+
+fn read_stats(reader: &mut impl Read) -> io::Result<SearchStats> {
+    let mut expanded = vec![];
+    for _ in 0..read_u64(reader)? {
+        expanded.push(read_u64(reader)?);
+    }
+    Ok(SearchStats {
+        expanded,
+        moves_played: read_u64(reader)?,
+        duplicates: read_u64(reader)?,
+        dead_ends: read_u64(reader)?,
+        over_bound: read_u64(reader)?,
+        pruned: read_u64(reader)?,
+        peak_frontier: read_u64(reader)? as usize,
+        peak_bytes: read_u64(reader)? as usize,
+        elapsed: Duration::from_nanos(read_u64(reader)?),
+    })
+}
+
+fn write_u64(writer: &mut impl Write, value: u64) -> io::Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+fn read_u64(reader: &mut impl Read) -> io::Result<u64> {
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+/// Like `read_bytes`, but the end of the file is an error
+fn read_some_bytes(reader: &mut impl Read) -> io::Result<Vec<u8>> {
+    read_bytes(reader)?.ok_or(io::ErrorKind::UnexpectedEof.into())
+}
+
+fn write_move(
+    writer: &mut impl Write,
+    Move {
+        match_type: move_type,
+        draws,
+        left,
+        right,
+    }: &Move,
+) -> io::Result<()> {
+    let move_type = match move_type {
+        MatchType::Board => 0,
+        MatchType::BoardStack => 1,
+        MatchType::Stack => 2,
+    };
+    writer.write_all(&[move_type, *draws as i8 as u8, left.0])?;
+    writer.write_all(&[right.map_or(u8::MAX, |card| card.0)])
+}
+
+fn read_move(reader: &mut impl Read) -> Result<Move> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    let move_type = match bytes[0] {
+        0 => MatchType::Board,
+        1 => MatchType::BoardStack,
+        2 => MatchType::Stack,
+        other => bail!("Unknown move type {}", other),
+    };
+    let right = match bytes[3] {
+        u8::MAX => None,
+        card => Some(RawCard(card)),
+    };
+    Ok(Move::new(
+        move_type,
+        bytes[1] as i8 as i32,
+        RawCard(bytes[2]),
+        right,
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::game::utils::Verbosity;
+    use crate::solver::optimal::{find_optimal_solutions, find_optimal_solutions_checkpointed};
+    use crate::test_utils::{readme_board, small_board};
+
+    #[test]
+    fn test_resume_gives_the_same_result() {
+        let board = small_board();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("checkpoint");
+        let checkpoints = Checkpoints {
+            path: path.clone(),
+            interval: Duration::ZERO,
+            resume: None,
+        };
+
+        // Every queue is checkpointed, so the file ends up holding the last queue played
+        let (expected, expected_stats) = find_optimal_solutions_checkpointed(
+            board.clone(),
+            60,
+            5,
+            Some(&checkpoints),
+            Verbosity::Off,
+        )
+        .unwrap();
+        let expected = expected.unwrap();
+        let state = load(&path, &board.clone(), 60).unwrap();
+        assert_eq!(state.queue_num, expected.moves as usize);
+
+        let resumed = Checkpoints {
+            resume: Some(path),
+            ..checkpoints
+        };
+        let (solutions, stats) = find_optimal_solutions_checkpointed(
+            board.clone(),
+            60,
+            5,
+            Some(&resumed),
+            Verbosity::Off,
+        )
+        .unwrap();
+        let solutions = solutions.unwrap();
+
+        // Only the last queue was searched again, on top of the stats saved with the checkpoint
+        assert_eq!(
+            SearchStats {
+                elapsed: Duration::ZERO,
+                ..stats
+            },
+            SearchStats {
+                elapsed: Duration::ZERO,
+                ..expected_stats
+            }
+        );
+        assert_eq!(solutions.moves, expected.moves);
+        assert_eq!(solutions.orderings, expected.orderings);
+        assert_eq!(solutions.solutions, expected.solutions);
+
+        let uncheckpointed = find_optimal_solutions(board, 60, 5, Verbosity::Off)
+            .unwrap()
+            .0
+            .unwrap();
+        assert_eq!(solutions.solutions, uncheckpointed.solutions);
+    }
+
+    #[test]
+    fn test_resume_checks_the_deal() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("checkpoint");
+        let state = SearchState::new(readme_board(), 60);
+        save(&path, &readme_board(), 60, &state).unwrap();
+
+        assert!(load(&path, &readme_board(), 60).is_ok());
+        assert!(load(&path, &readme_board(), 50).is_err());
+
+        let mut other = readme_board();
+        other.play_move(other.get_moves()[0]);
+        assert!(load(&path, &other, 60).is_err());
+
+        let board = readme_board();
+        let clear_all = Board::new(
+            board.board_cards.to_vec(),
+            board.stack,
+            (21..28).collect(),
+            true,
+        );
+        let error = load(&path, &clear_all, 60).err().unwrap();
+        assert_eq!(
+            error.root_cause().to_string(),
+            "The checkpoint is for clearing the board, not the board and the stack"
+        );
+    }
+}
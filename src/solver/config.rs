@@ -0,0 +1,237 @@
+use crate::solver::bfs::Solution;
+use crate::solver::learned::MoveScorer;
+use rand::Rng;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Settings for how a search runs, and when it has to give up
+#[derive(Debug, Clone, Default)]
+pub struct SolverConfig {
+    /// Number of threads to search with, rayon's global pool is used when not set
+    pub threads: Option<usize>,
+    /// Stop once the search stores more states than this, counting the positions it has seen and
+    /// the boards waiting to be played, with or without `deterministic`
+    pub max_states: Option<usize>,
+    /// Stop once the states the search stores take up an estimated number of bytes over this
+    pub max_bytes: Option<usize>,
+    /// Stop after searching for this long
+    pub time_limit: Option<Duration>,
+    /// Stop once the token is cancelled
+    pub cancel: Option<CancellationToken>,
+    /// Give the same result on every run, however many threads there are
+    pub deterministic: bool,
+    /// Order the moves of each board by their score instead of by draws and card
+    pub move_scorer: Option<MoveScorer>,
+}
+
+/// How many moves the default search plays from each board
+///
+/// Moves that need no draws are always played. Of the others, only the first `first_top_moves`
+/// are played for the first `first_games` moves of the game, and the first `top_moves` after that.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Heuristics {
+    pub first_top_moves: usize,
+    pub first_games: usize,
+    pub top_moves: usize,
+}
+
+impl Heuristics {
+    pub const DEFAULT: Heuristics = Heuristics {
+        first_top_moves: 3,
+        first_games: 5,
+        top_moves: 2,
+    };
+
+    /// Slower, but tries more options
+    pub const INCREASED: Heuristics = Heuristics {
+        first_top_moves: 5,
+        first_games: 10,
+        top_moves: 3,
+    };
+
+    /// Every combination of settings that's worth trying
+    pub fn grid() -> Vec<Heuristics> {
+        let mut grid = vec![];
+        for first_top_moves in 1..=6 {
+            for first_games in [0, 5, 10, 20] {
+                for top_moves in 1..=4 {
+                    grid.push(Heuristics {
+                        first_top_moves,
+                        first_games,
+                        top_moves,
+                    });
+                }
+            }
+        }
+        grid
+    }
+
+    /// Settings picked at random from the same ranges as `grid`
+    pub fn random(rng: &mut impl Rng) -> Heuristics {
+        Heuristics {
+            first_top_moves: rng.gen_range(1..=6),
+            first_games: rng.gen_range(0..=20),
+            top_moves: rng.gen_range(1..=4),
+        }
+    }
+}
+
+/// A flag to stop a search from another thread
+///
+/// Clones share the same flag
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> CancellationToken {
+        CancellationToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Why a search stopped before it was done, serialized as `"time_limit"`, `"cancelled"`,
+/// `"max_states"` or `"max_bytes"`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AbortReason {
+    TimeLimit,
+    Cancelled,
+    MaxStates,
+    MaxBytes,
+}
+
+impl fmt::Display for AbortReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self {
+            AbortReason::TimeLimit => "it ran out of time",
+            AbortReason::Cancelled => "it was cancelled",
+            AbortReason::MaxStates => "it stored too many states",
+            AbortReason::MaxBytes => "it used too much memory",
+        };
+        write!(f, "{}", reason)
+    }
+}
+
+/// How a search run with a `SolverConfig` ended
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SearchOutcome {
+    /// The search ran to the end, with a partial solution if there was none within the max depth
+    Done(Solution),
+    /// The search stopped early, with the best line it found before that
+    Aborted { reason: AbortReason, best: Solution },
+}
+
+impl SearchOutcome {
+    pub fn solution(&self) -> &Solution {
+        match self {
+            SearchOutcome::Done(solution) => solution,
+            SearchOutcome::Aborted { best, .. } => best,
+        }
+    }
+
+    pub fn into_solution(self) -> Solution {
+        match self {
+            SearchOutcome::Done(solution) => solution,
+            SearchOutcome::Aborted { best, .. } => best,
+        }
+    }
+}
+
+/// The limits of a `SolverConfig`, for a search that has started
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Limits {
+    pub(crate) deadline: Option<Instant>,
+    pub(crate) max_states: Option<usize>,
+    pub(crate) max_bytes: Option<usize>,
+    pub(crate) cancel: Option<CancellationToken>,
+}
+
+impl Limits {
+    /// Start counting down the time limit of the config
+    pub(crate) fn new(config: &SolverConfig) -> Limits {
+        Limits {
+            deadline: config
+                .time_limit
+                .map(|time_limit| Instant::now() + time_limit),
+            max_states: config.max_states,
+            max_bytes: config.max_bytes,
+            cancel: config.cancel.clone(),
+        }
+    }
+
+    /// Why the search has to stop, given how many states it stores and their estimated size
+    pub(crate) fn exceeded(&self, states: usize, bytes: usize) -> Option<AbortReason> {
+        if self
+            .cancel
+            .as_ref()
+            .is_some_and(|cancel| cancel.is_cancelled())
+        {
+            Some(AbortReason::Cancelled)
+        } else if self
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            Some(AbortReason::TimeLimit)
+        } else if self
+            .max_states
+            .is_some_and(|max_states| states > max_states)
+        {
+            Some(AbortReason::MaxStates)
+        } else if self.max_bytes.is_some_and(|max_bytes| bytes > max_bytes) {
+            Some(AbortReason::MaxBytes)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_grid() {
+        let grid = Heuristics::grid();
+        assert_eq!(grid.len(), 96);
+        assert!(grid.contains(&Heuristics::DEFAULT));
+        assert!(grid.contains(&Heuristics::INCREASED));
+    }
+
+    #[test]
+    fn test_limits_exceeded() {
+        let cancel = CancellationToken::new();
+        let limits = Limits::new(&SolverConfig {
+            max_states: Some(10),
+            max_bytes: Some(1000),
+            cancel: Some(cancel.clone()),
+            ..SolverConfig::default()
+        });
+
+        assert_eq!(limits.exceeded(10, 1000), None);
+        assert_eq!(limits.exceeded(11, 0), Some(AbortReason::MaxStates));
+        assert_eq!(limits.exceeded(0, 1001), Some(AbortReason::MaxBytes));
+
+        cancel.clone().cancel();
+        assert_eq!(limits.exceeded(0, 0), Some(AbortReason::Cancelled));
+    }
+
+    #[test]
+    fn test_limits_time_limit() {
+        let limits = Limits::new(&SolverConfig {
+            time_limit: Some(Duration::ZERO),
+            ..SolverConfig::default()
+        });
+
+        assert_eq!(limits.exceeded(0, 0), Some(AbortReason::TimeLimit));
+        assert_eq!(Limits::default().exceeded(usize::MAX, usize::MAX), None);
+    }
+}
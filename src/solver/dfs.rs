@@ -0,0 +1,320 @@
+use crate::game::board::Board;
+use crate::game::utils::Verbosity;
+use crate::solver::bfs::Solution;
+use crate::solver::stats::SearchStats;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::mem::size_of;
+use std::time::Instant;
+
+/// A fixed size table of positions already searched in the current iteration
+///
+/// Each position hashes to a single slot, and a new position simply replaces whatever was in its
+/// slot, so the table never grows. The whole position is kept in the slot, so that positions
+/// sharing a slot are never mistaken for each other.
+struct TranspositionTable {
+    slots: Vec<Slot>,
+}
+
+#[derive(Clone, Default)]
+struct Slot {
+    position: Vec<u8>,
+    /// Iteration the position was stored in
+    iteration: u32,
+    /// Fewest moves the position was reached in
+    moves: i32,
+}
+
+impl TranspositionTable {
+    fn new(size: usize) -> TranspositionTable {
+        TranspositionTable {
+            slots: vec![Slot::default(); size.max(1)],
+        }
+    }
+
+    /// Record reaching a position, returning false if it was already searched this iteration with
+    /// at least as many moves to spare
+    fn visit(&mut self, board: &Board, iteration: u32) -> bool {
+        let position = board.get_position();
+        let mut hasher = DefaultHasher::new();
+        position.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        let len = self.slots.len();
+        let slot = &mut self.slots[(hash % len as u64) as usize];
+        if slot.iteration == iteration && slot.moves <= board.moves && slot.position == position {
+            return false;
+        }
+        *slot = Slot {
+            position,
+            iteration,
+            moves: board.moves,
+        };
+        true
+    }
+
+    /// Bytes taken up by the slots and the positions in them
+    fn bytes(&self) -> usize {
+        self.slots
+            .iter()
+            .map(|slot| size_of::<Slot>() + slot.position.capacity())
+            .sum()
+    }
+}
+
+/// Search for a solution depth first, deepening the search one limit at a time
+///
+/// Each iteration searches every line whose moves made plus `Board::lower_bound_moves` stay within
+/// a limit, and the next one raises the limit to the smallest value that went over it. As the
+/// bound never overestimates, the first solution found is one of the shortest the moves from
+/// `Board::get_moves` can reach. Moves are played and taken back on a single board, and
+/// positions already searched are kept in a table of `table_size` entries, so memory use stays
+/// the same however long the search runs.
+///
+/// Returns the line that leaves the fewest cards if there's no solution within the max depth, or
+/// the deadline is reached first. The stats add up every iteration, with the longest line
+/// searched as the frontier.
+pub fn depth_first_search(
+    board: Board,
+    max_depth: usize,
+    table_size: usize,
+    deadline: Option<Instant>,
+    verbosity: Verbosity,
+) -> (Solution, SearchStats) {
+    let start = Instant::now();
+    let mut search = DepthFirst {
+        best_partial: Solution::from_board(&board, vec![]),
+        board,
+        line: vec![],
+        table: TranspositionTable::new(table_size),
+        iteration: 0,
+        limit: 0,
+        next_limit: i32::MAX,
+        max_depth: max_depth as i32,
+        deadline,
+        nodes: 0,
+        aborted: false,
+        stats: SearchStats::default(),
+    };
+    search.limit = search.board.lower_bound_moves();
+
+    while search.limit < search.max_depth {
+        search.iteration += 1;
+        search.next_limit = i32::MAX;
+        search.nodes = 0;
+
+        let found = search.dfs();
+        let table_bytes = search.table.bytes();
+        search.stats.record_frontier(0, table_bytes);
+
+        if verbosity >= Verbosity::Low {
+            println!("Limit {} - boards searched: {}", search.limit, search.nodes);
+        }
+        if let Some(solution) = found {
+            search.stats.elapsed = start.elapsed();
+            return (solution, search.stats);
+        }
+        if search.aborted || search.out_of_time() || search.next_limit == i32::MAX {
+            break;
+        }
+        search.limit = search.next_limit;
+    }
+
+    search.stats.elapsed = start.elapsed();
+    (search.best_partial, search.stats)
+}
+
+struct DepthFirst {
+    board: Board,
+    /// The moves played to get from the starting board to `board`
+    line: Vec<usize>,
+    best_partial: Solution,
+    table: TranspositionTable,
+    iteration: u32,
+    /// Lines that need more moves than this aren't searched in this iteration
+    limit: i32,
+    /// The smallest number of moves over the limit that a line needed
+    next_limit: i32,
+    max_depth: i32,
+    deadline: Option<Instant>,
+    nodes: usize,
+    /// The deadline passed, so the search is being unwound without looking at any more boards
+    aborted: bool,
+    stats: SearchStats,
+}
+
+impl DepthFirst {
+    fn out_of_time(&self) -> bool {
+        self.deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    fn dfs(&mut self) -> Option<Solution> {
+        self.nodes += 1;
+        // Checking the clock on every board would slow things down a fair bit
+        if self.nodes.is_multiple_of(1024) && self.out_of_time() {
+            self.aborted = true;
+            return None;
+        }
+        if self.board.completed {
+            if self.board.moves <= self.limit {
+                return Some(Solution::from_board(&self.board, self.line.clone()));
+            }
+            // The bound let through a line longer than the limit, which a shorter one may beat
+            self.next_limit = self.next_limit.min(self.board.moves);
+            self.stats.over_bound += 1;
+            return None;
+        }
+
+        let line = Solution::from_board(&self.board, vec![]);
+        if line.is_better_than(&self.best_partial) {
+            self.best_partial = Solution {
+                moves_played: self.line.clone(),
+                ..line
+            };
+        }
+
+        let needed = self.board.moves + self.board.lower_bound_moves();
+        if needed > self.limit {
+            self.next_limit = self.next_limit.min(needed);
+            self.stats.over_bound += 1;
+            return None;
+        }
+        if self.board.is_dead() {
+            self.stats.dead_ends += 1;
+            return None;
+        }
+        if !self.table.visit(&self.board, self.iteration) {
+            self.stats.duplicates += 1;
+            return None;
+        }
+        self.stats.add_expanded(self.board.moves as usize, 1);
+        self.stats.record_frontier(self.line.len(), 0);
+
+        for (idx, r#move) in self.board.get_moves().into_iter().enumerate() {
+            let draws = r#move.draws;
+            if draws + self.board.moves + 1 >= self.max_depth {
+                break;
+            }
+
+            let undo = self.board.play_move_with_undo(r#move);
+            self.stats.moves_played += 1;
+            self.line.push(idx + 1);
+            let found = self.dfs();
+            self.line.pop();
+            self.board.undo_move(undo);
+
+            if found.is_some() || self.aborted {
+                return found;
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::game::card::RawCard;
+    use crate::solver::optimal::find_optimal_solutions;
+    use crate::test_utils::{readme_board, small_board, top_rows_board};
+
+    #[test]
+    fn test_depth_first_search_small_board() {
+        let (solution, stats) = depth_first_search(small_board(), 60, 1024, None, Verbosity::Off);
+
+        assert!(!solution.is_partial());
+        assert_eq!(solution.moves, 6);
+        assert!(stats.nodes_expanded() > 0);
+        assert!(stats.peak_frontier >= 5);
+        assert!(stats.peak_bytes >= 1024 * size_of::<Slot>());
+    }
+
+    #[test]
+    fn test_depth_first_search_shortest_with_draws() {
+        let stack: Vec<RawCard> = [44, 48, 2, 45, 11, 15, 21, 3, 49, 17, 16]
+            .into_iter()
+            .map(RawCard)
+            .collect();
+        let board = top_rows_board([47, 25, 50, 13, 14, 9, 5, 19, 51, 8], stack);
+
+        let optimal = find_optimal_solutions(board.clone(), 30, 1, Verbosity::Off)
+            .unwrap()
+            .0
+            .unwrap();
+        let (solution, _) = depth_first_search(board, 30, 1 << 12, None, Verbosity::Off);
+
+        // The shortest solution needs draws, which the bound can underestimate
+        assert!(optimal.moves as usize > optimal.solutions[0].len());
+        assert!(!solution.is_partial());
+        assert_eq!(solution.moves, optimal.moves);
+    }
+
+    #[test]
+    fn test_transposition_table_shared_slot() {
+        let board = small_board();
+        let mut played = board.clone();
+        played.play_move(played.get_moves()[0]);
+
+        // Every position shares the only slot, without being mistaken for another
+        let mut table = TranspositionTable::new(1);
+        assert!(table.visit(&board, 1));
+        assert!(!table.visit(&board, 1));
+        assert!(table.visit(&played, 1));
+        assert!(table.visit(&board, 1));
+        assert!(table.visit(&board, 2));
+    }
+
+    #[test]
+    fn test_depth_first_search_max_depth() {
+        // Six moves are needed, so a max depth of 6 leaves no room for the last one
+        let (solution, _) = depth_first_search(small_board(), 6, 1024, None, Verbosity::Off);
+        assert!(solution.is_partial());
+
+        let (solution, _) = depth_first_search(small_board(), 7, 1024, None, Verbosity::Off);
+        assert!(!solution.is_partial());
+    }
+
+    #[test]
+    fn test_depth_first_search_stops_at_deadline() {
+        let board = readme_board();
+        let mut search = DepthFirst {
+            best_partial: Solution::from_board(&board, vec![]),
+            board,
+            line: vec![],
+            table: TranspositionTable::new(1 << 16),
+            iteration: 1,
+            // An iteration that searches tens of thousands of boards
+            limit: 41,
+            next_limit: i32::MAX,
+            max_depth: 60,
+            deadline: Some(Instant::now()),
+            nodes: 0,
+            aborted: false,
+            stats: SearchStats::default(),
+        };
+
+        assert!(search.dfs().is_none());
+        assert!(search.aborted);
+        assert_eq!(search.nodes, 1024);
+        assert!(search.line.is_empty());
+    }
+
+    #[test]
+    fn test_depth_first_search_tiny_table() {
+        let board = readme_board();
+
+        let deadline = Some(Instant::now() + std::time::Duration::from_millis(200));
+        let (solution, _) = depth_first_search(board.clone(), 60, 16, deadline, Verbosity::Off);
+
+        // Whatever was found has to replay to what it claims
+        let mut board = board;
+        for move_num in solution.moves_played.iter() {
+            let r#move = board.get_moves()[*move_num - 1];
+            board.play_move(r#move);
+        }
+        assert_eq!(board.moves, solution.moves);
+        assert_eq!(board.cards_left(), solution.cards_left);
+    }
+}
@@ -0,0 +1,150 @@
+use crate::game::blocks::card_blocked_by;
+use crate::game::board::Board;
+use crate::game::card::Card;
+use crate::game::utils::{cards_match, match_card};
+
+/// Scores how promising a board is, higher is better
+pub trait Evaluator: Sync {
+    fn evaluate(&self, board: &Board) -> i32;
+}
+
+/// Fewer cards left to clear is better
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CardsCleared;
+
+impl Evaluator for CardsCleared {
+    fn evaluate(&self, board: &Board) -> i32 {
+        -(board.cards_left() as i32)
+    }
+}
+
+/// More cards exposed on the board gives more options
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ExposedLeaves;
+
+impl Evaluator for ExposedLeaves {
+    fn evaluate(&self, board: &Board) -> i32 {
+        board.leaf_idxs.len() as i32
+    }
+}
+
+/// Penalise cards on the board that cover one of their own matches
+///
+/// Such a pair can never be matched together, so the covered card has to find another partner.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BlockedPairs;
+
+impl Evaluator for BlockedPairs {
+    fn evaluate(&self, board: &Board) -> i32 {
+        let remaining = board.remaining_idxs();
+
+        let blocked = remaining
+            .iter()
+            .map(|&idx| {
+                card_blocked_by(idx)
+                    .into_iter()
+                    .filter(|blocker| remaining.contains(blocker))
+                    .filter(|&blocker| {
+                        cards_match(board.board_cards[idx], board.board_cards[blocker])
+                    })
+                    .count()
+            })
+            .sum::<usize>();
+
+        -(blocked as i32)
+    }
+}
+
+/// Reward exposed cards that have a match somewhere in the stack
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StackReachability;
+
+impl Evaluator for StackReachability {
+    fn evaluate(&self, board: &Board) -> i32 {
+        board
+            .leaves()
+            .into_iter()
+            .filter(|&leaf| {
+                let leaf_match = match_card(leaf.into());
+                board
+                    .stack
+                    .iter()
+                    .any(|&card| Card::from(card) == leaf_match)
+            })
+            .count() as i32
+    }
+}
+
+/// A weighted sum of other evaluators
+pub struct Weighted(pub Vec<(i32, Box<dyn Evaluator>)>);
+
+impl Evaluator for Weighted {
+    fn evaluate(&self, board: &Board) -> i32 {
+        self.0
+            .iter()
+            .map(|(weight, evaluator)| weight * evaluator.evaluate(board))
+            .sum()
+    }
+}
+
+impl Default for Weighted {
+    /// All of the built-in evaluators, with clearing cards weighing the most
+    fn default() -> Self {
+        Weighted(vec![
+            (10, Box::new(CardsCleared)),
+            (2, Box::new(ExposedLeaves)),
+            (5, Box::new(BlockedPairs)),
+            (1, Box::new(StackReachability)),
+        ])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::game::card::RawCard;
+    use crate::test_utils::top_rows_board;
+
+    fn get_board() -> Board {
+        // Top four rows left, where the 3 at the top is covered by a 10
+        top_rows_board(
+            [2, 4, 7, 1, 10, 12, 0, 11, 3, 9],
+            vec![RawCard(44), RawCard(45)],
+        )
+    }
+
+    #[test]
+    fn test_cards_cleared() {
+        assert_eq!(CardsCleared.evaluate(&get_board()), -10);
+    }
+
+    #[test]
+    fn test_exposed_leaves() {
+        assert_eq!(ExposedLeaves.evaluate(&get_board()), 4);
+    }
+
+    #[test]
+    fn test_blocked_pairs() {
+        assert_eq!(BlockedPairs.evaluate(&get_board()), -1);
+    }
+
+    #[test]
+    fn test_stack_reachability() {
+        // The stack has a 6 and a 7, which don't match any of the exposed cards
+        let mut board = get_board();
+        assert_eq!(StackReachability.evaluate(&board), 0);
+
+        // Until a 6 is exposed
+        board.board_cards[9] = RawCard(5);
+        assert_eq!(StackReachability.evaluate(&board), 1);
+    }
+
+    #[test]
+    fn test_weighted() {
+        let evaluator = Weighted(vec![
+            (3, Box::new(ExposedLeaves)),
+            (1, Box::new(CardsCleared)),
+        ]);
+        assert_eq!(evaluator.evaluate(&get_board()), 2);
+    }
+}
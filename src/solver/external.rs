@@ -0,0 +1,417 @@
+use crate::game::board::Board;
+use crate::game::utils::Verbosity;
+use crate::solver::bfs::{estimated_bytes, Solution};
+use crate::solver::stats::SearchStats;
+use anyhow::{Context, Result};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use tempfile::TempDir;
+
+/// A board waiting to be played, as it's stored on disk
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Record {
+    /// `Board::get_position`, which records are sorted and told apart by
+    position: Vec<u8>,
+    /// `Board::snapshot`
+    snapshot: Vec<u8>,
+    moves_made: Vec<usize>,
+}
+
+impl Record {
+    fn new(board: &Board, moves_made: Vec<usize>) -> Record {
+        Record {
+            position: board.get_position(),
+            snapshot: board.snapshot(),
+            moves_made,
+        }
+    }
+
+    fn write(&self, writer: &mut impl Write) -> io::Result<()> {
+        let moves_made = self
+            .moves_made
+            .iter()
+            .map(|&move_num| {
+                u8::try_from(move_num).map_err(|_| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Move {} doesn't fit in a byte", move_num),
+                    )
+                })
+            })
+            .collect::<io::Result<Vec<u8>>>()?;
+        write_bytes(writer, &self.position)?;
+        write_bytes(writer, &self.snapshot)?;
+        write_bytes(writer, &moves_made)
+    }
+
+    fn read(reader: &mut impl Read) -> io::Result<Option<Record>> {
+        let Some(position) = read_bytes(reader)? else {
+            return Ok(None);
+        };
+        let snapshot = read_bytes(reader)?.ok_or(io::ErrorKind::UnexpectedEof)?;
+        let moves_made = read_bytes(reader)?.ok_or(io::ErrorKind::UnexpectedEof)?;
+        Ok(Some(Record {
+            position,
+            snapshot,
+            moves_made: moves_made.into_iter().map(usize::from).collect(),
+        }))
+    }
+}
+
+/// Write a run of up to 65535 bytes, prefixed by its length
+pub(crate) fn write_bytes(writer: &mut impl Write, bytes: &[u8]) -> io::Result<()> {
+    let len = u16::try_from(bytes.len()).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} bytes don't fit in a run", bytes.len()),
+        )
+    })?;
+    writer.write_all(&len.to_le_bytes())?;
+    writer.write_all(bytes)
+}
+
+/// Read a length prefixed run of bytes, or `None` at the end of the file
+pub(crate) fn read_bytes(reader: &mut impl Read) -> io::Result<Option<Vec<u8>>> {
+    let mut len = [0u8; 2];
+    match reader.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(err) => return Err(err),
+    }
+    let mut bytes = vec![0u8; u16::from_le_bytes(len) as usize];
+    reader.read_exact(&mut bytes)?;
+    Ok(Some(bytes))
+}
+
+fn open_reader(path: &Path) -> Result<BufReader<File>> {
+    let file = File::open(path).with_context(|| format!("Couldn't open {}", path.display()))?;
+    Ok(BufReader::new(file))
+}
+
+fn create_writer(path: &Path) -> Result<BufWriter<File>> {
+    let file = File::create(path).with_context(|| format!("Couldn't create {}", path.display()))?;
+    Ok(BufWriter::new(file))
+}
+
+/// Search every move of every board, keeping the boards waiting to be played on disk
+///
+/// Works like `find_optimal_solutions`, one move count at a time, but the boards for each move
+/// count are appended to a file instead of kept in memory. Before a move count is played, its file
+/// is sorted in runs of `run_size` boards that are merged back together, which drops duplicate
+/// boards and boards whose position was already played in fewer moves. Those positions are kept
+/// in another sorted file. Memory use is set by `run_size` rather than the size of the search.
+///
+/// The files go in a temporary directory, inside `dir` if one is given. Returns one of the
+/// shortest solutions, or the line that leaves the fewest cards if there's no solution within the
+/// max depth, with the stats of the search. Their memory estimate only covers the boards sorted
+/// in memory, not the files.
+pub fn external_search(
+    board: Board,
+    max_depth: usize,
+    run_size: usize,
+    dir: Option<&Path>,
+    verbosity: Verbosity,
+) -> Result<(Solution, SearchStats)> {
+    let start = Instant::now();
+    let temp_dir = match dir {
+        Some(dir) => TempDir::new_in(dir),
+        None => TempDir::new(),
+    }
+    .context("Couldn't create a directory for the search files")?;
+
+    let mut search = ExternalSearch {
+        dir: temp_dir.path().to_path_buf(),
+        run_size: run_size.max(1),
+        max_depth,
+        writers: (0..max_depth).map(|_| None).collect(),
+        best_partial: Solution::from_board(&board, vec![]),
+        best_moves: i32::MAX,
+        start: board.clone(),
+        stats: SearchStats::default(),
+    };
+    search.push(&Record::new(&board, vec![]), 0)?;
+
+    for layer in 0..max_depth {
+        // A king on the left of the stack is removed without using up a move, so playing a move
+        // count can add more boards to it
+        while let Some(frontier) = search.next_frontier(layer, verbosity)? {
+            if let Some(solution) = search.play_frontier(&frontier, layer)? {
+                search.stats.elapsed = start.elapsed();
+                return Ok((solution, search.stats));
+            }
+            fs::remove_file(&frontier)?;
+        }
+    }
+
+    search.stats.elapsed = start.elapsed();
+    Ok((search.best_partial, search.stats))
+}
+
+struct ExternalSearch {
+    dir: PathBuf,
+    run_size: usize,
+    max_depth: usize,
+    /// Where the boards for each move count are being appended
+    writers: Vec<Option<BufWriter<File>>>,
+    best_partial: Solution,
+    best_moves: i32,
+    /// The board the search started from, which the others are rebuilt from
+    start: Board,
+    stats: SearchStats,
+}
+
+impl ExternalSearch {
+    fn layer_path(&self, layer: usize) -> PathBuf {
+        self.dir.join(format!("layer-{}", layer))
+    }
+
+    fn seen_path(&self) -> PathBuf {
+        self.dir.join("seen")
+    }
+
+    fn push(&mut self, record: &Record, layer: usize) -> Result<()> {
+        if self.writers[layer].is_none() {
+            let path = self.layer_path(layer);
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)
+                .with_context(|| format!("Couldn't open {}", path.display()))?;
+            self.writers[layer] = Some(BufWriter::new(file));
+        }
+        record.write(self.writers[layer].as_mut().unwrap())?;
+        Ok(())
+    }
+
+    /// Sort and deduplicate the boards added to a move count, returning the file of the ones that
+    /// still need playing, if there are any
+    fn next_frontier(&mut self, layer: usize, verbosity: Verbosity) -> Result<Option<PathBuf>> {
+        let Some(mut writer) = self.writers[layer].take() else {
+            return Ok(None);
+        };
+        writer.flush()?;
+        drop(writer);
+
+        let layer_path = self.layer_path(layer);
+        let (runs, boards) = self.write_runs(&layer_path)?;
+        fs::remove_file(&layer_path)?;
+
+        let frontier_path = self.dir.join(format!("frontier-{}", layer));
+        let new_seen_path = self.dir.join("seen-new");
+        let mut frontier = create_writer(&frontier_path)?;
+        let mut new_seen = create_writer(&new_seen_path)?;
+        let mut seen = match self.seen_path().exists() {
+            true => Some(open_reader(&self.seen_path())?),
+            false => None,
+        };
+        let mut next_seen = match seen.as_mut() {
+            Some(seen) => read_bytes(seen)?,
+            None => None,
+        };
+
+        // Merge the sorted runs, keeping the first of each position, and skip the positions that
+        // were already played, merging the new ones into the seen positions as we go
+        let mut readers = runs
+            .iter()
+            .map(|run| open_reader(run))
+            .collect::<Result<Vec<_>>>()?;
+        let mut heap = BinaryHeap::new();
+        for (idx, reader) in readers.iter_mut().enumerate() {
+            if let Some(record) = Record::read(reader)? {
+                heap.push(Reverse((record, idx)));
+            }
+        }
+
+        let mut last_position: Option<Vec<u8>> = None;
+        let mut new_boards = 0;
+        while let Some(Reverse((record, idx))) = heap.pop() {
+            if let Some(next) = Record::read(&mut readers[idx])? {
+                heap.push(Reverse((next, idx)));
+            }
+            if last_position.as_ref() == Some(&record.position) {
+                continue;
+            }
+
+            while let Some(position) = next_seen.take_if(|position| *position < record.position) {
+                write_bytes(&mut new_seen, &position)?;
+                next_seen = read_bytes(seen.as_mut().unwrap())?;
+            }
+            if next_seen.as_ref() != Some(&record.position) {
+                write_bytes(&mut new_seen, &record.position)?;
+                record.write(&mut frontier)?;
+                new_boards += 1;
+            }
+            last_position = Some(record.position);
+        }
+        while let Some(position) = next_seen {
+            write_bytes(&mut new_seen, &position)?;
+            next_seen = read_bytes(seen.as_mut().unwrap())?;
+        }
+
+        frontier.flush()?;
+        new_seen.flush()?;
+        fs::rename(&new_seen_path, self.seen_path())?;
+        for run in runs {
+            fs::remove_file(run)?;
+        }
+
+        self.stats.duplicates += (boards - new_boards) as u64;
+        self.stats
+            .record_frontier(boards, estimated_bytes(self.run_size.min(boards)));
+
+        if verbosity >= Verbosity::Low {
+            println!("Queue {} - size: {} - new: {}", layer, boards, new_boards);
+        }
+
+        Ok(Some(frontier_path))
+    }
+
+    /// Split a file of boards into sorted runs of at most `run_size` boards, without duplicates
+    fn write_runs(&self, path: &Path) -> Result<(Vec<PathBuf>, usize)> {
+        let mut reader = open_reader(path)?;
+        let mut runs = vec![];
+        let mut boards = 0;
+
+        loop {
+            let mut run = vec![];
+            while run.len() < self.run_size {
+                match Record::read(&mut reader)? {
+                    Some(record) => run.push(record),
+                    None => break,
+                }
+            }
+            if run.is_empty() {
+                break;
+            }
+            boards += run.len();
+
+            run.sort_unstable();
+            run.dedup_by(|a, b| a.position == b.position);
+
+            let run_path = self.dir.join(format!("run-{}", runs.len()));
+            let mut writer = create_writer(&run_path)?;
+            for record in run {
+                record.write(&mut writer)?;
+            }
+            writer.flush()?;
+            runs.push(run_path);
+        }
+
+        Ok((runs, boards))
+    }
+
+    /// Play every move from the boards in a frontier file, returning a solution if one of them is
+    /// completed
+    fn play_frontier(&mut self, path: &Path, layer: usize) -> Result<Option<Solution>> {
+        let mut reader = open_reader(path)?;
+
+        while let Some(record) = Record::read(&mut reader)? {
+            let board = self.start.restore_snapshot(&record.snapshot);
+            if board.completed {
+                return Ok(Some(Solution::from_board(&board, record.moves_made)));
+            }
+
+            let line = Solution::from_board(&board, vec![]);
+            if line.is_better_than(&self.best_partial) {
+                self.best_partial = Solution {
+                    moves_played: record.moves_made.clone(),
+                    ..line
+                };
+            }
+            self.stats.add_expanded(layer, 1);
+
+            for (idx, r#move) in board.get_moves().into_iter().enumerate() {
+                let draws = r#move.draws;
+                if draws + board.moves + 1 >= self.max_depth as i32 {
+                    break;
+                }
+
+                let mut new_board = board.clone();
+                new_board.play_move(r#move);
+                self.stats.moves_played += 1;
+
+                if new_board.completed {
+                    self.best_moves = self.best_moves.min(new_board.moves);
+                } else if new_board.is_dead() {
+                    self.stats.dead_ends += 1;
+                    continue;
+                } else if new_board.moves + new_board.lower_bound_moves() >= self.best_moves {
+                    self.stats.over_bound += 1;
+                    continue;
+                }
+
+                let mut moves_made = record.moves_made.clone();
+                moves_made.push(idx + 1);
+                let new_layer = new_board.moves as usize;
+                debug_assert!(new_layer >= layer);
+                self.push(&Record::new(&new_board, moves_made), new_layer)?;
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::solver::optimal::find_optimal_solutions;
+    use crate::test_utils::{readme_board, small_board};
+
+    #[test]
+    fn test_record_round_trip() {
+        let board = readme_board();
+        let record = Record::new(&board, vec![1, 3, 2]);
+
+        let mut bytes = vec![];
+        record.write(&mut bytes).unwrap();
+        record.write(&mut bytes).unwrap();
+
+        let mut reader = &bytes[..];
+        assert_eq!(Record::read(&mut reader).unwrap(), Some(record.clone()));
+        assert_eq!(Record::read(&mut reader).unwrap(), Some(record));
+        assert_eq!(Record::read(&mut reader).unwrap(), None);
+
+        let error = Record::new(&board, vec![1, 300])
+            .write(&mut vec![])
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_long_runs() {
+        let mut bytes = vec![];
+        let long: Vec<u8> = (0..300).map(|byte| byte as u8).collect();
+        write_bytes(&mut bytes, &long).unwrap();
+        write_bytes(&mut bytes, &[7]).unwrap();
+
+        let mut reader = bytes.as_slice();
+        assert_eq!(read_bytes(&mut reader).unwrap(), Some(long));
+        assert_eq!(read_bytes(&mut reader).unwrap(), Some(vec![7]));
+        assert_eq!(read_bytes(&mut reader).unwrap(), None);
+
+        assert!(write_bytes(&mut vec![], &vec![0; 1 << 16]).is_err());
+    }
+
+    #[test]
+    fn test_external_search_matches_optimal() {
+        let board = small_board();
+
+        // Tiny runs, so boards get merged from a lot of files
+        let (solution, stats) =
+            external_search(board.clone(), 60, 2, None, Verbosity::Off).unwrap();
+        let optimal = find_optimal_solutions(board, 60, 1, Verbosity::Off)
+            .unwrap()
+            .0
+            .unwrap();
+
+        assert!(!solution.is_partial());
+        assert_eq!(solution.moves, optimal.moves);
+        assert!(stats.nodes_expanded() > 0);
+        assert!(stats.peak_bytes <= estimated_bytes(2));
+    }
+}
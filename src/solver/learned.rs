@@ -0,0 +1,362 @@
+use crate::game::board::Board;
+use crate::game::card::{Card, MatchType};
+use crate::game::r#move::Move;
+use crate::solver::bfs::Solution;
+use crate::solver::evaluate::{BlockedPairs, Evaluator, ExposedLeaves, StackReachability};
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Names of the features `move_features` describes a move with, in order
+pub const FEATURE_NAMES: [&str; 12] = [
+    "bias",
+    "draws",
+    "board_match",
+    "board_stack_match",
+    "stack_match",
+    "king",
+    "board_cards_removed",
+    "top_row_removed",
+    "exposed_leaves",
+    "blocked_pairs",
+    "stack_reachability",
+    "lower_bound",
+];
+
+/// Describe a move by what kind it is and how it changes the board
+///
+/// Changes are measured as the value after the move minus the value before it. The lower bound
+/// change includes the moves the move itself takes.
+pub fn move_features(board: &Board, r#move: Move) -> [f64; 12] {
+    let Move {
+        match_type: move_type,
+        draws,
+        left,
+        right,
+    } = r#move;
+    let mut after = board.clone();
+    after.play_move(r#move);
+
+    let board_rows: Vec<usize> = [Some(left), right]
+        .into_iter()
+        .flatten()
+        .filter_map(|card| {
+            board
+                .remaining_idxs()
+                .into_iter()
+                .find(|&idx| board.board_cards[idx] == card)
+        })
+        .map(row)
+        .collect();
+    let change =
+        |evaluator: &dyn Evaluator| (evaluator.evaluate(&after) - evaluator.evaluate(board)) as f64;
+
+    [
+        1.0,
+        draws as f64,
+        (move_type == MatchType::Board) as u8 as f64,
+        (move_type == MatchType::BoardStack) as u8 as f64,
+        (move_type == MatchType::Stack) as u8 as f64,
+        (Card::from(left).0 == 13) as u8 as f64,
+        board_rows.len() as f64,
+        board_rows.iter().min().copied().unwrap_or(7) as f64,
+        change(&ExposedLeaves),
+        change(&BlockedPairs),
+        change(&StackReachability),
+        ((after.moves + after.lower_bound_moves()) - (board.moves + board.lower_bound_moves()))
+            as f64,
+    ]
+}
+
+/// The row of the pyramid a board card is in, 0 at the top
+fn row(idx: usize) -> usize {
+    (0..7).rev().find(|row| row * (row + 1) / 2 <= idx).unwrap()
+}
+
+/// A move that could be played along a solved line, and how the game went
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrainingExample {
+    pub features: [f64; 12],
+    /// The move was the one played in the line
+    pub chosen: bool,
+    /// The line cleared the board
+    pub solved: bool,
+    /// Moves the line still made from this board
+    pub moves_left: i32,
+}
+
+/// Every move available along a line found by a search, marking the ones that were played
+pub fn training_examples(board: &Board, solution: &Solution) -> Vec<TrainingExample> {
+    let mut board = board.clone();
+    let mut examples = vec![];
+    for &move_num in solution.moves_played.iter() {
+        let moves = board.get_moves();
+        for (idx, r#move) in moves.iter().enumerate() {
+            examples.push(TrainingExample {
+                features: move_features(&board, *r#move),
+                chosen: idx + 1 == move_num,
+                solved: !solution.is_partial(),
+                moves_left: solution.moves - board.moves,
+            });
+        }
+        board.play_move(moves[move_num - 1]);
+    }
+    examples
+}
+
+/// Write examples as CSV, with a header of the feature names
+pub fn write_training_csv(writer: &mut impl Write, examples: &[TrainingExample]) -> io::Result<()> {
+    writeln!(
+        writer,
+        "{},chosen,solved,moves_left",
+        FEATURE_NAMES.join(",")
+    )?;
+    for example in examples {
+        let features: Vec<String> = example.features.iter().map(f64::to_string).collect();
+        writeln!(
+            writer,
+            "{},{},{},{}",
+            features.join(","),
+            example.chosen as u8,
+            example.solved as u8,
+            example.moves_left
+        )?;
+    }
+    Ok(())
+}
+
+/// Read examples back from CSV written by `write_training_csv`
+pub fn read_training_csv(text: &str) -> Result<Vec<TrainingExample>> {
+    let mut lines = text.lines().enumerate();
+    let header = format!("{},chosen,solved,moves_left", FEATURE_NAMES.join(","));
+    match lines.next() {
+        Some((_, line)) if line.trim() == header => {}
+        _ => bail!("Line 1 should be the header: {}", header),
+    }
+
+    let mut examples = vec![];
+    for (idx, line) in lines {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let values: Vec<&str> = line.trim().split(',').collect();
+        if values.len() != FEATURE_NAMES.len() + 3 {
+            bail!(
+                "Line {} should have {} values, not {}",
+                idx + 1,
+                FEATURE_NAMES.len() + 3,
+                values.len()
+            );
+        }
+        let parse = |value: &str| -> Result<f64> {
+            value
+                .parse()
+                .with_context(|| format!("Line {} has an invalid value: {}", idx + 1, value))
+        };
+
+        let mut features = [0.0; 12];
+        for (feature, value) in features.iter_mut().zip(values.iter()) {
+            *feature = parse(value)?;
+        }
+        let n = FEATURE_NAMES.len();
+        examples.push(TrainingExample {
+            features,
+            chosen: parse(values[n])? != 0.0,
+            solved: parse(values[n + 1])? != 0.0,
+            moves_left: parse(values[n + 2])? as i32,
+        });
+    }
+    Ok(examples)
+}
+
+/// Orders moves by a weighted sum of their features, highest first
+#[derive(Debug, Clone, PartialEq)]
+pub struct MoveScorer {
+    pub weights: [f64; 12],
+}
+
+impl MoveScorer {
+    /// Read weights from text with a feature name and a weight on each line
+    ///
+    /// Empty lines and lines starting with # are skipped, and features that aren't given weigh 0
+    pub fn parse(text: &str) -> Result<MoveScorer> {
+        let mut weights = [0.0; 12];
+        for (idx, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (name, weight) = match line.split_whitespace().collect::<Vec<_>>()[..] {
+                [name, weight] => (name, weight),
+                _ => bail!("Line {} should have a feature name and a weight", idx + 1),
+            };
+            let feature = match FEATURE_NAMES.iter().position(|&feature| feature == name) {
+                Some(feature) => feature,
+                None => bail!("Line {} has an unknown feature: {}", idx + 1, name),
+            };
+            weights[feature] = weight
+                .parse()
+                .with_context(|| format!("Line {} has an invalid weight: {}", idx + 1, weight))?;
+        }
+        Ok(MoveScorer { weights })
+    }
+
+    pub fn load(path: &Path) -> Result<MoveScorer> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Couldn't read {}", path.display()))?;
+        MoveScorer::parse(&text).with_context(|| format!("Couldn't load {}", path.display()))
+    }
+
+    /// The weights in the format `parse` reads
+    pub fn to_text(&self) -> String {
+        FEATURE_NAMES
+            .iter()
+            .zip(self.weights.iter())
+            .map(|(name, weight)| format!("{} {}\n", name, weight))
+            .collect()
+    }
+
+    pub fn score(&self, board: &Board, r#move: Move) -> f64 {
+        self.score_features(&move_features(board, r#move))
+    }
+
+    fn score_features(&self, features: &[f64; 12]) -> f64 {
+        self.weights
+            .iter()
+            .zip(features.iter())
+            .map(|(weight, feature)| weight * feature)
+            .sum()
+    }
+
+    /// Sort moves from the highest score to the lowest, keeping the order of equal scores
+    pub(crate) fn sort(&self, board: &Board, moves: &mut [(usize, Move)]) {
+        let mut scored: Vec<(f64, (usize, Move))> = moves
+            .iter()
+            .map(|&(idx, r#move)| (self.score(board, r#move), (idx, r#move)))
+            .collect();
+        scored.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+        for (slot, (_, r#move)) in moves.iter_mut().zip(scored) {
+            *slot = r#move;
+        }
+    }
+
+    /// Fit the weights to predict which moves solved lines played, by logistic regression
+    ///
+    /// Only examples from solved lines are used
+    pub fn fit(examples: &[TrainingExample], epochs: usize, learning_rate: f64) -> MoveScorer {
+        let mut scorer = MoveScorer { weights: [0.0; 12] };
+        let examples: Vec<&TrainingExample> =
+            examples.iter().filter(|example| example.solved).collect();
+        if examples.is_empty() {
+            return scorer;
+        }
+
+        for _ in 0..epochs {
+            let mut gradient = [0.0; 12];
+            for example in examples.iter() {
+                let predicted = 1.0 / (1.0 + (-scorer.score_features(&example.features)).exp());
+                let error = predicted - example.chosen as u8 as f64;
+                for (total, feature) in gradient.iter_mut().zip(example.features.iter()) {
+                    *total += error * feature;
+                }
+            }
+            for (weight, total) in scorer.weights.iter_mut().zip(gradient) {
+                *weight -= learning_rate * total / examples.len() as f64;
+            }
+        }
+        scorer
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::{readme_board, solve};
+
+    #[test]
+    fn test_move_features() {
+        let board = readme_board();
+        // The first move is the king on the board
+        let r#move = board.get_moves()[0];
+        let features = move_features(&board, r#move);
+
+        let feature = |name| features[FEATURE_NAMES.iter().position(|&f| f == name).unwrap()];
+
+        assert_eq!(feature("king"), 1.0);
+        assert_eq!(feature("board_match"), 1.0);
+        assert_eq!(feature("board_cards_removed"), 1.0);
+        assert_eq!(feature("top_row_removed"), 6.0);
+        assert_eq!(feature("lower_bound"), 0.0);
+        assert_eq!(row(0), 0);
+        assert_eq!(row(2), 1);
+        assert_eq!(row(27), 6);
+    }
+
+    #[test]
+    fn test_training_csv_round_trip() {
+        let board = readme_board();
+        let solution = solve(&board);
+        let examples = training_examples(&board, &solution);
+
+        let chosen = examples.iter().filter(|example| example.chosen).count();
+        assert_eq!(chosen, solution.moves_played.len());
+        assert_eq!(examples[0].moves_left, solution.moves);
+
+        let mut csv = vec![];
+        write_training_csv(&mut csv, &examples).unwrap();
+        let read = read_training_csv(&String::from_utf8(csv).unwrap()).unwrap();
+        assert_eq!(read, examples);
+
+        assert!(read_training_csv("draws,chosen\n").is_err());
+    }
+
+    #[test]
+    fn test_move_scorer() {
+        let scorer = MoveScorer::parse("# Fewer draws first\ndraws -1\n\nking 10\n").unwrap();
+        assert_eq!(scorer.weights[1], -1.0);
+        assert_eq!(MoveScorer::parse(&scorer.to_text()).unwrap(), scorer);
+
+        let err = MoveScorer::parse("draws -1\nrandom 2\n").unwrap_err();
+        assert_eq!(err.to_string(), "Line 2 has an unknown feature: random");
+
+        // Moves come out from the highest score to the lowest
+        let scorer = MoveScorer::parse("stack_match 1\ndraws -0.5\nexposed_leaves 0.2").unwrap();
+        let mut board = readme_board();
+        board.play_move(board.get_moves()[0]);
+        let mut moves: Vec<(usize, Move)> = board.get_moves().into_iter().enumerate().collect();
+        scorer.sort(&board, &mut moves);
+
+        let scores: Vec<f64> = moves
+            .iter()
+            .map(|&(_, r#move)| scorer.score(&board, r#move))
+            .collect();
+        assert!(scores.windows(2).all(|pair| pair[0] >= pair[1]));
+        assert_eq!(moves.len(), board.get_moves().len());
+    }
+
+    #[test]
+    fn test_fit() {
+        // The chosen moves are the ones without draws
+        let example = |draws: f64, chosen: bool| {
+            let mut features = [0.0; 12];
+            features[0] = 1.0;
+            features[1] = draws;
+            TrainingExample {
+                features,
+                chosen,
+                solved: true,
+                moves_left: 10,
+            }
+        };
+        let examples = vec![
+            example(0.0, true),
+            example(3.0, false),
+            example(0.0, true),
+            example(5.0, false),
+        ];
+
+        let scorer = MoveScorer::fit(&examples, 200, 0.5);
+        assert!(scorer.weights[1] < 0.0);
+    }
+}
@@ -0,0 +1,338 @@
+use crate::game::board::Board;
+use crate::game::r#move::Move;
+use crate::game::utils::Verbosity;
+use crate::solver::bfs::{estimated_bytes, Solution};
+use crate::solver::stats::SearchStats;
+use rand::Rng;
+use std::time::Instant;
+
+/// How moves are picked when playing out a game from a new node
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Playout {
+    /// Any move, uniformly at random
+    Random,
+    /// Mostly the first move `Board::get_moves` offers, which is the one needing the fewest draws
+    Heuristic,
+}
+
+struct Node {
+    board: Board,
+    parent: Option<usize>,
+    /// 1-based index of the move into the parent's `Board::get_moves`
+    move_num: usize,
+    children: Vec<usize>,
+    /// Moves that haven't been expanded into children yet
+    untried: Vec<usize>,
+    visits: u32,
+    reward: f64,
+}
+
+impl Node {
+    fn new(board: Board, parent: Option<usize>, move_num: usize, max_depth: usize) -> Node {
+        let untried = (1..=legal_moves(&board, max_depth).len()).collect();
+        Node {
+            board,
+            parent,
+            move_num,
+            children: vec![],
+            untried,
+            visits: 0,
+            reward: 0.0,
+        }
+    }
+
+    fn uct(&self, parent_visits: u32, exploration: f64) -> f64 {
+        self.reward / self.visits as f64
+            + exploration * ((parent_visits as f64).ln() / self.visits as f64).sqrt()
+    }
+}
+
+/// The moves that can be played without going over the max depth
+///
+/// The moves keep the order from `Board::get_moves`, so their indexes match
+fn legal_moves(board: &Board, max_depth: usize) -> Vec<Move> {
+    if board.completed {
+        return vec![];
+    }
+    board
+        .get_moves()
+        .into_iter()
+        .take_while(|Move { draws, .. }| *draws + board.moves + 1 < max_depth as i32)
+        .collect()
+}
+
+/// Search for a solution with Monte Carlo tree search
+///
+/// For every move of the game, a tree is grown for `iterations` iterations from the current
+/// board, using `exploration` as the UCT exploration constant, and the most visited move is
+/// played. Each iteration plays a game out to the end with the given playout policy. If any
+/// playout along the way solved the board in fewer moves than the line that was played, that
+/// playout is returned instead. Moves and playouts are picked at random with `rng`.
+///
+/// The stats count the tree nodes expanded by their move count, and the largest tree as the
+/// frontier.
+pub fn mcts_search(
+    board: Board,
+    max_depth: usize,
+    iterations: usize,
+    exploration: f64,
+    playout: Playout,
+    rng: &mut impl Rng,
+    verbosity: Verbosity,
+) -> (Solution, SearchStats) {
+    let start = Instant::now();
+    let mut stats = SearchStats::default();
+    let start_left = board.cards_left().max(1);
+    let mut best = Solution::from_board(&board, vec![]);
+
+    let mut current = board;
+    let mut line: Vec<usize> = vec![];
+
+    loop {
+        let moves = legal_moves(&current, max_depth);
+        if moves.is_empty() {
+            break;
+        }
+
+        let move_num = if moves.len() == 1 {
+            1
+        } else {
+            let mut search = TreeSearch {
+                nodes: vec![Node::new(current.clone(), None, 0, max_depth)],
+                line: &line,
+                max_depth,
+                start_left,
+                exploration,
+                playout,
+                rng,
+                best: &mut best,
+                stats: &mut stats,
+            };
+            for _ in 0..iterations {
+                search.iterate();
+            }
+            let tree_size = search.nodes.len();
+            let move_num = search.most_visited();
+            stats.record_frontier(tree_size, estimated_bytes(tree_size));
+            move_num
+        };
+
+        if verbosity >= Verbosity::Low {
+            println!(
+                "Move {} - {} options - playing option {}",
+                current.moves,
+                moves.len(),
+                move_num
+            );
+        }
+
+        current.play_move(moves[move_num - 1]);
+        line.push(move_num);
+        stats.moves_played += 1;
+
+        let played = Solution::from_board(&current, line.clone());
+        if played.is_better_than(&best) {
+            best = played;
+        }
+    }
+
+    stats.elapsed = start.elapsed();
+    (best, stats)
+}
+
+struct TreeSearch<'a, R: Rng> {
+    nodes: Vec<Node>,
+    /// The moves played before the root of the tree
+    line: &'a [usize],
+    max_depth: usize,
+    start_left: usize,
+    exploration: f64,
+    playout: Playout,
+    rng: &'a mut R,
+    best: &'a mut Solution,
+    stats: &'a mut SearchStats,
+}
+
+impl<R: Rng> TreeSearch<'_, R> {
+    fn iterate(&mut self) {
+        // Select
+        let mut node = 0;
+        while self.nodes[node].untried.is_empty() && !self.nodes[node].children.is_empty() {
+            let parent_visits = self.nodes[node].visits;
+            node = *self.nodes[node]
+                .children
+                .iter()
+                .max_by(|a, b| {
+                    let a = self.nodes[**a].uct(parent_visits, self.exploration);
+                    let b = self.nodes[**b].uct(parent_visits, self.exploration);
+                    a.total_cmp(&b)
+                })
+                .unwrap();
+        }
+
+        // Expand
+        if !self.nodes[node].untried.is_empty() {
+            let pick = self.rng.gen_range(0..self.nodes[node].untried.len());
+            let move_num = self.nodes[node].untried.swap_remove(pick);
+
+            let mut board = self.nodes[node].board.clone();
+            let r#move = legal_moves(&board, self.max_depth)[move_num - 1];
+            self.stats.add_expanded(board.moves as usize, 1);
+            board.play_move(r#move);
+            self.stats.moves_played += 1;
+
+            self.nodes
+                .push(Node::new(board, Some(node), move_num, self.max_depth));
+            let child = self.nodes.len() - 1;
+            self.nodes[node].children.push(child);
+            node = child;
+        }
+
+        // Simulate
+        let reward = self.simulate(node);
+
+        // Backpropagate
+        let mut current = Some(node);
+        while let Some(idx) = current {
+            self.nodes[idx].visits += 1;
+            self.nodes[idx].reward += reward;
+            current = self.nodes[idx].parent;
+        }
+    }
+
+    /// Play a game out from a node, returning the reward for how it ended
+    fn simulate(&mut self, node: usize) -> f64 {
+        let mut board = self.nodes[node].board.clone();
+        let mut playout_moves: Vec<usize> = vec![];
+
+        loop {
+            let moves = legal_moves(&board, self.max_depth);
+            if moves.is_empty() {
+                break;
+            }
+            let move_num = match self.playout {
+                Playout::Random => self.rng.gen_range(1..=moves.len()),
+                Playout::Heuristic => {
+                    if self.rng.gen_bool(0.8) {
+                        1
+                    } else {
+                        self.rng.gen_range(1..=moves.len())
+                    }
+                }
+            };
+            board.play_move(moves[move_num - 1]);
+            playout_moves.push(move_num);
+        }
+        self.stats.moves_played += playout_moves.len() as u64;
+
+        let cards_left = board.cards_left();
+        if cards_left <= self.best.cards_left {
+            let mut moves_played = self.line.to_vec();
+            moves_played.extend(self.tree_path(node));
+            moves_played.extend(playout_moves);
+            let playout = Solution::from_board(&board, moves_played);
+            if playout.is_better_than(self.best) {
+                *self.best = playout;
+            }
+        }
+
+        if board.completed {
+            // Between 0.5 and 1, with shorter solutions being better
+            0.5 + 0.5 * (1.0 - board.moves as f64 / self.max_depth as f64)
+        } else {
+            // Up to 0.5 for the cards cleared
+            0.5 * (1.0 - cards_left as f64 / self.start_left as f64)
+        }
+    }
+
+    /// The moves from the root of the tree to a node
+    fn tree_path(&self, node: usize) -> Vec<usize> {
+        let mut path = vec![];
+        let mut current = node;
+        while let Some(parent) = self.nodes[current].parent {
+            path.push(self.nodes[current].move_num);
+            current = parent;
+        }
+        path.reverse();
+        path
+    }
+
+    /// The move of the most visited child of the root, or the first move if none was expanded
+    fn most_visited(&self) -> usize {
+        self.nodes[0]
+            .children
+            .iter()
+            .max_by_key(|child| self.nodes[**child].visits)
+            .map_or(1, |child| self.nodes[*child].move_num)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::{readme_board, small_board};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_mcts_search_solves_small_board() {
+        let board = small_board();
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let (solution, stats) = mcts_search(
+            board,
+            60,
+            50,
+            1.4,
+            Playout::Random,
+            &mut rng,
+            Verbosity::Off,
+        );
+
+        assert!(!solution.is_partial());
+        assert_eq!(solution.moves, 6);
+        assert!(stats.nodes_expanded() > 0);
+        assert!(stats.peak_frontier > 1);
+    }
+
+    #[test]
+    fn test_mcts_search_line_replays() {
+        let board = readme_board();
+
+        let (solution, _) = mcts_search(
+            board.clone(),
+            60,
+            20,
+            1.4,
+            Playout::Heuristic,
+            &mut StdRng::seed_from_u64(0),
+            Verbosity::Off,
+        );
+
+        let mut board = board;
+        for move_num in solution.moves_played.iter() {
+            let r#move = board.get_moves()[*move_num - 1];
+            board.play_move(r#move);
+        }
+        assert_eq!(board.moves, solution.moves);
+        assert_eq!(board.cards_left(), solution.cards_left);
+    }
+
+    #[test]
+    fn test_mcts_search_without_iterations() {
+        let board = readme_board();
+
+        // The first move is played whenever there's nothing to go on
+        let (solution, stats) = mcts_search(
+            board,
+            60,
+            0,
+            1.4,
+            Playout::Random,
+            &mut StdRng::seed_from_u64(0),
+            Verbosity::Off,
+        );
+        assert!(solution.moves_played.iter().all(|&move_num| move_num == 1));
+        assert_eq!(stats.nodes_expanded(), 0);
+    }
+}
@@ -0,0 +1,15 @@
+pub(crate) mod advisor;
+pub(crate) mod beam;
+pub(crate) mod bfs;
+pub(crate) mod checkpoint;
+pub(crate) mod config;
+pub(crate) mod dfs;
+pub mod evaluate;
+pub(crate) mod external;
+pub(crate) mod learned;
+pub(crate) mod mcts;
+pub(crate) mod optimal;
+pub(crate) mod optimize;
+pub(crate) mod report;
+pub(crate) mod stats;
+pub(crate) mod tune;
@@ -0,0 +1,484 @@
+use crate::game::blocks::card_blocked_by;
+use crate::game::board::Board;
+use crate::game::card::{MatchType, RawCard};
+use crate::game::r#move::Move;
+use crate::game::utils::Verbosity;
+use crate::solver::bfs::estimated_bytes;
+use crate::solver::checkpoint::{self, Checkpoints};
+use crate::solver::stats::SearchStats;
+use anyhow::Result;
+use rayon::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::time::Instant;
+
+pub(crate) type State = Vec<u8>;
+pub(crate) type Edge = (State, usize, Move);
+
+/// Most move sequences walked to tell the distinct solutions apart
+///
+/// Every ordering of independent moves is a sequence of its own, so there can be far too many of
+/// them to walk through on a real deal.
+const MAX_WALKED_LINES: usize = 1_000_000;
+
+/// The shortest solutions for a board
+#[derive(Debug, Clone)]
+pub struct OptimalSolutions {
+    /// Number of moves, including draws, that every shortest solution takes
+    pub moves: i32,
+    /// Number of move sequences that solve the board in `moves`, counting every ordering of
+    /// independent moves separately
+    pub orderings: u64,
+    /// One move sequence per distinct solution, as 1-based indexes into `Board::get_moves`
+    ///
+    /// Sequences that only differ by the order of independent moves are the same solution
+    pub solutions: Vec<Vec<usize>>,
+    /// The listing stopped at the requested maximum, or after walking through `MAX_WALKED_LINES`
+    /// move sequences, so there can be more distinct solutions than `solutions` holds
+    pub truncated: bool,
+}
+
+/// Find every shortest solution for the board
+///
+/// Unlike `simulate_games`, this plays every move `Board::get_moves` returns, so the search is
+/// exhaustive up to `max_depth` and can get slow on hard deals. At most `max_solutions` distinct
+/// solutions are collected. The stats of the search are returned along with them.
+pub fn find_optimal_solutions(
+    board: Board,
+    max_depth: usize,
+    max_solutions: usize,
+    verbosity: Verbosity,
+) -> Result<(Option<OptimalSolutions>, SearchStats)> {
+    find_optimal_solutions_checkpointed(board, max_depth, max_solutions, None, verbosity)
+}
+
+/// Everything the search needs to carry on from the start of a queue
+pub(crate) struct SearchState {
+    pub(crate) queue_num: usize,
+    pub(crate) queues: Vec<Vec<Board>>,
+    /// The moves leading to each state that was reached, with the state they were played from
+    pub(crate) parents: HashMap<State, Vec<Edge>>,
+    /// What the search did so far, including before it was resumed
+    pub(crate) stats: SearchStats,
+    /// Fewest moves of any solution found so far. Boards that can only tie it are still played,
+    /// as they can lead to other shortest solutions.
+    pub(crate) best_moves: i32,
+}
+
+impl SearchState {
+    pub(crate) fn new(board: Board, max_depth: usize) -> SearchState {
+        let mut parents = HashMap::new();
+        parents.insert(board.get_state(), vec![]);
+
+        let mut queues = vec![vec![]; max_depth];
+        queues[0].push(board);
+
+        SearchState {
+            queue_num: 0,
+            queues,
+            parents,
+            stats: SearchStats::default(),
+            best_moves: max_depth as i32 - 1,
+        }
+    }
+}
+
+/// Find every shortest solution for the board, saving the progress of the search as it goes
+///
+/// Same as `find_optimal_solutions`, but the search state is written to a checkpoint file at the
+/// start of a queue whenever the checkpoint interval has passed. Resuming from a checkpoint
+/// written for the same board and max depth gives the same result as the search that wrote it,
+/// and the stats carry on from the ones saved with the checkpoint.
+pub fn find_optimal_solutions_checkpointed(
+    board: Board,
+    max_depth: usize,
+    max_solutions: usize,
+    checkpoints: Option<&Checkpoints>,
+    verbosity: Verbosity,
+) -> Result<(Option<OptimalSolutions>, SearchStats)> {
+    let board_cards = board.board_cards;
+    let mut state = match checkpoints.and_then(|checkpoints| checkpoints.resume.as_deref()) {
+        Some(path) => {
+            let state = checkpoint::load(path, &board, max_depth)?;
+            if verbosity >= Verbosity::Low {
+                println!("Resuming from queue {}", state.queue_num);
+            }
+            state
+        }
+        None => SearchState::new(board.clone(), max_depth),
+    };
+    let mut last_checkpoint = Instant::now();
+    let start = Instant::now();
+    let elapsed_before = state.stats.elapsed;
+
+    while state.queue_num < max_depth {
+        if let Some(checkpoints) = checkpoints {
+            if last_checkpoint.elapsed() >= checkpoints.interval {
+                state.stats.elapsed = elapsed_before + start.elapsed();
+                checkpoint::save(&checkpoints.path, &board, max_depth, &state)?;
+                last_checkpoint = Instant::now();
+            }
+        }
+
+        let queue_num = state.queue_num;
+        let mut completed: Vec<(State, i32)> = vec![];
+
+        // Removing a king from the left of the stack doesn't add to the move count, so boards can
+        // keep getting added to the queue being processed
+        loop {
+            let queue = std::mem::take(&mut state.queues[queue_num]);
+            if queue.is_empty() {
+                break;
+            }
+            state
+                .stats
+                .record_frontier(queue.len(), estimated_bytes(state.parents.len()));
+
+            if verbosity >= Verbosity::Low {
+                println!(
+                    "Queue {} - size: {} - states: {} - dead ends: {} - over bound: {}",
+                    queue_num,
+                    queue.len(),
+                    state.parents.len(),
+                    state.stats.dead_ends,
+                    state.stats.over_bound
+                );
+            }
+
+            completed.extend(
+                queue
+                    .iter()
+                    .filter(|board| board.completed)
+                    .map(|board| (board.get_state(), board.moves)),
+            );
+            let expanded = queue.iter().filter(|board| !board.completed).count();
+            state.stats.add_expanded(queue_num, expanded as u64);
+
+            let children: Vec<(Edge, Board)> = queue
+                .par_iter()
+                .filter(|board| !board.completed)
+                .flat_map_iter(|board| {
+                    let state = board.get_state();
+                    board
+                        .get_moves()
+                        .into_iter()
+                        .enumerate()
+                        .take_while(move |(_, Move { draws, .. })| {
+                            *draws + board.moves + 1 < max_depth as i32
+                        })
+                        .map(move |(idx, r#move)| {
+                            let mut new_board = board.clone();
+                            new_board.play_move(r#move);
+                            ((state.clone(), idx + 1, r#move), new_board)
+                        })
+                })
+                .collect();
+
+            state.stats.moves_played += children.len() as u64;
+            for (edge, new_board) in children {
+                match state.parents.get_mut(&new_board.get_state()) {
+                    Some(edges) => {
+                        edges.push(edge);
+                        state.stats.duplicates += 1;
+                    }
+                    None => {
+                        state.parents.insert(new_board.get_state(), vec![edge]);
+                        // Dead boards are still recorded, so they are only checked once
+                        if new_board.completed {
+                            state.best_moves = state.best_moves.min(new_board.moves);
+                        }
+                        if new_board.is_dead() {
+                            state.stats.dead_ends += 1;
+                        } else if new_board.moves + new_board.lower_bound_moves() > state.best_moves
+                        {
+                            state.stats.over_bound += 1;
+                        } else {
+                            state.queues[new_board.moves as usize].push(new_board);
+                        }
+                    }
+                }
+            }
+        }
+
+        if let Some(&(_, moves)) = completed.first() {
+            let solutions = collect_solutions(
+                &board_cards,
+                &state.parents,
+                &completed,
+                moves,
+                max_solutions,
+                MAX_WALKED_LINES,
+            );
+            state.stats.elapsed = elapsed_before + start.elapsed();
+            return Ok((Some(solutions), state.stats));
+        }
+
+        state.queue_num += 1;
+    }
+
+    state.stats.elapsed = elapsed_before + start.elapsed();
+    Ok((None, state.stats))
+}
+
+/// Tell apart the distinct solutions among the move sequences that lead to the completed states
+///
+/// The listing is truncated after `max_solutions` solutions, or once `max_lines` sequences have
+/// been walked through.
+fn collect_solutions(
+    board_cards: &[RawCard; 28],
+    parents: &HashMap<State, Vec<Edge>>,
+    completed: &[(State, i32)],
+    moves: i32,
+    max_solutions: usize,
+    max_lines: usize,
+) -> OptimalSolutions {
+    let mut orderings_memo: HashMap<State, u64> = HashMap::new();
+    let orderings = completed
+        .iter()
+        .map(|(state, _)| count_orderings(parents, state, &mut orderings_memo))
+        .sum();
+
+    let mut walk = SolutionWalk {
+        board_cards,
+        parents,
+        path: vec![],
+        seen: HashSet::new(),
+        solutions: vec![],
+        max_solutions,
+        lines_left: max_lines,
+    };
+    let truncated = !completed.iter().all(|(state, _)| walk.walk(state));
+    let solutions = walk.solutions;
+
+    OptimalSolutions {
+        moves,
+        orderings,
+        solutions,
+        truncated,
+    }
+}
+
+fn count_orderings(
+    parents: &HashMap<State, Vec<Edge>>,
+    state: &State,
+    memo: &mut HashMap<State, u64>,
+) -> u64 {
+    if let Some(count) = memo.get(state) {
+        return *count;
+    }
+    let edges = &parents[state];
+    let count = if edges.is_empty() {
+        1
+    } else {
+        edges
+            .iter()
+            .map(|(parent, _, _)| count_orderings(parents, parent, memo))
+            .fold(0u64, |acc, count| acc.saturating_add(count))
+    };
+    memo.insert(state.clone(), count);
+    count
+}
+
+/// The distinct solutions found so far while walking back through the parents of the completed
+/// states
+struct SolutionWalk<'a> {
+    board_cards: &'a [RawCard; 28],
+    parents: &'a HashMap<State, Vec<Edge>>,
+    /// The moves walked back through from the completed state, last move first
+    path: Vec<(usize, Move)>,
+    seen: HashSet<Vec<Move>>,
+    solutions: Vec<Vec<usize>>,
+    max_solutions: usize,
+    /// Move sequences that can still be walked through
+    lines_left: usize,
+}
+
+impl SolutionWalk<'_> {
+    /// Walk back from a state to the initial board, recording every distinct solution on the way
+    ///
+    /// Returns false once `max_solutions` solutions have been found and there are more to find,
+    /// or there are sequences left to walk after `lines_left` runs out
+    fn walk(&mut self, state: &State) -> bool {
+        let edges = &self.parents[state];
+        if edges.is_empty() {
+            if self.lines_left == 0 {
+                return false;
+            }
+            self.lines_left -= 1;
+
+            let moves: Vec<Move> = self.path.iter().rev().map(|(_, r#move)| *r#move).collect();
+            if self.seen.insert(canonical_order(self.board_cards, &moves)) {
+                if self.solutions.len() == self.max_solutions {
+                    return false;
+                }
+                let solution = self.path.iter().rev().map(|(move_num, _)| *move_num);
+                self.solutions.push(solution.collect());
+            }
+            return true;
+        }
+
+        for (parent, move_num, r#move) in edges {
+            self.path.push((*move_num, *r#move));
+            let more = self.walk(parent);
+            self.path.pop();
+            if !more {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Indexes on the board of the pyramid cards a move removes
+fn pyramid_idxs(board_cards: &[RawCard; 28], Move { left, right, .. }: &Move) -> Vec<usize> {
+    [Some(*left), *right]
+        .into_iter()
+        .flatten()
+        .filter_map(|card| board_cards.iter().position(|&x| x == card))
+        .collect()
+}
+
+/// Check if two moves can be played in either order with the same result
+///
+/// That's the case when neither needs draws, at most one of them touches the stack, and the
+/// pyramid cards they remove don't cover each other.
+pub(crate) fn moves_commute(board_cards: &[RawCard; 28], a: &Move, b: &Move) -> bool {
+    if a.draws != 0 || b.draws != 0 {
+        return false;
+    }
+    if a.match_type != MatchType::Board && b.match_type != MatchType::Board {
+        return false;
+    }
+
+    let b_idxs = pyramid_idxs(board_cards, b);
+    pyramid_idxs(board_cards, a).iter().all(|a_idx| {
+        b_idxs.iter().all(|b_idx| {
+            a_idx != b_idx
+                && !card_blocked_by(*a_idx).contains(b_idx)
+                && !card_blocked_by(*b_idx).contains(a_idx)
+        })
+    })
+}
+
+/// Reorder a solution so that independent moves are played in ascending order
+///
+/// Two solutions that only differ by the order of independent moves end up identical.
+pub(crate) fn canonical_order(board_cards: &[RawCard; 28], moves: &[Move]) -> Vec<Move> {
+    let mut remaining: Vec<Move> = moves.to_vec();
+    let mut ordered: Vec<Move> = Vec::with_capacity(moves.len());
+
+    while !remaining.is_empty() {
+        // Any move that commutes with every move before it could be played next
+        let next = (0..remaining.len())
+            .filter(|&pos| {
+                remaining[..pos]
+                    .iter()
+                    .all(|earlier| moves_commute(board_cards, earlier, &remaining[pos]))
+            })
+            .min_by_key(|&pos| remaining[pos])
+            .unwrap();
+        ordered.push(remaining.remove(next));
+    }
+
+    ordered
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::small_board;
+
+    #[test]
+    fn test_find_optimal_solutions_counts_orderings_once() {
+        let optimal = find_optimal_solutions(small_board(), 60, 10, Verbosity::Off)
+            .unwrap()
+            .0
+            .unwrap();
+
+        assert_eq!(optimal.moves, 6);
+        assert_eq!(optimal.orderings, 2);
+        assert_eq!(optimal.solutions.len(), 1);
+        assert!(!optimal.truncated);
+    }
+
+    #[test]
+    fn test_find_optimal_solutions_max_depth() {
+        let (optimal, stats) =
+            find_optimal_solutions(small_board(), 6, 10, Verbosity::Off).unwrap();
+        assert!(optimal.is_none());
+        assert!(stats.nodes_expanded() > 0);
+    }
+
+    #[test]
+    fn test_find_optimal_solutions_truncated() {
+        let optimal = find_optimal_solutions(small_board(), 60, 0, Verbosity::Off)
+            .unwrap()
+            .0
+            .unwrap();
+
+        assert!(optimal.solutions.is_empty());
+        assert!(optimal.truncated);
+    }
+
+    #[test]
+    fn test_collect_solutions_max_lines() {
+        let board = small_board();
+        let ace_queen: Move = Move::new(MatchType::Board, 0, RawCard(0), Some(RawCard(11)));
+        let three_ten: Move = Move::new(MatchType::Board, 0, RawCard(2), Some(RawCard(9)));
+
+        // Both orders of two independent moves lead to the same state
+        let (start, ace_first, ten_first, end) = (vec![0], vec![1], vec![2], vec![3]);
+        let parents: HashMap<State, Vec<Edge>> = HashMap::from([
+            (start.clone(), vec![]),
+            (ace_first.clone(), vec![(start.clone(), 1, ace_queen)]),
+            (ten_first.clone(), vec![(start, 2, three_ten)]),
+            (
+                end.clone(),
+                vec![(ace_first, 2, three_ten), (ten_first, 1, ace_queen)],
+            ),
+        ]);
+        let completed = [(end, 2)];
+
+        let optimal = collect_solutions(&board.board_cards, &parents, &completed, 2, 10, 2);
+        assert_eq!(optimal.solutions.len(), 1);
+        assert!(!optimal.truncated);
+
+        // The second order can't be told apart from a new solution without walking it
+        let optimal = collect_solutions(&board.board_cards, &parents, &completed, 2, 10, 1);
+        assert_eq!(optimal.solutions.len(), 1);
+        assert!(optimal.truncated);
+    }
+
+    #[test]
+    fn test_moves_commute() {
+        let board = small_board();
+
+        let ace_queen: Move = Move::new(MatchType::Board, 0, RawCard(0), Some(RawCard(11)));
+        let three_ten: Move = Move::new(MatchType::Board, 0, RawCard(2), Some(RawCard(9)));
+        let two_jack: Move = Move::new(MatchType::Board, 0, RawCard(1), Some(RawCard(10)));
+        let drawn: Move = Move::new(MatchType::Board, 1, RawCard(2), Some(RawCard(9)));
+
+        assert!(moves_commute(&board.board_cards, &ace_queen, &three_ten));
+        // The two covers the ace and the queen
+        assert!(!moves_commute(&board.board_cards, &ace_queen, &two_jack));
+        // Draws change what's visible on the stack
+        assert!(!moves_commute(&board.board_cards, &ace_queen, &drawn));
+    }
+
+    #[test]
+    fn test_canonical_order() {
+        let board = small_board();
+
+        let ace_queen: Move = Move::new(MatchType::Board, 0, RawCard(0), Some(RawCard(11)));
+        let three_ten: Move = Move::new(MatchType::Board, 0, RawCard(2), Some(RawCard(9)));
+        let king: Move = Move::new(MatchType::Board, 0, RawCard(12), None);
+
+        assert_eq!(
+            canonical_order(&board.board_cards, &[three_ten, ace_queen, king]),
+            vec![ace_queen, three_ten, king]
+        );
+        assert_eq!(
+            canonical_order(&board.board_cards, &[ace_queen, three_ten, king]),
+            vec![ace_queen, three_ten, king]
+        );
+    }
+}
@@ -0,0 +1,200 @@
+use crate::game::board::Board;
+use crate::game::card::RawCard;
+use crate::game::utils::Verbosity;
+use crate::solver::dfs::depth_first_search;
+use std::collections::HashSet;
+
+/// A solution before and after `optimize_solution`
+#[derive(Debug, Clone)]
+pub struct Optimized {
+    /// The moves played, as 1-based indexes into `Board::get_moves`
+    pub moves_played: Vec<usize>,
+    /// Moves made, including draws, by the solution as it was given
+    pub moves_before: i32,
+    /// Moves made, including draws, after optimizing
+    pub moves_after: i32,
+}
+
+/// Shorten a solution by replacing runs of up to `window` moves with cheaper ones
+///
+/// A run is replaced when the same position can be reached in fewer moves made, by removing the
+/// same cards in another order or drawing fewer cards to get to them. The last moves of a solution
+/// that clears the board are also replaced by the shortest way to clear it from there. This is
+/// repeated until no run can be improved, so independent moves get reordered and draws merged
+/// wherever that saves moves.
+pub fn optimize_solution(
+    board: &Board,
+    moves_played: &[usize],
+    window: usize,
+    verbosity: Verbosity,
+) -> Optimized {
+    let mut moves_played = moves_played.to_vec();
+    let moves_before = play_line(board, &moves_played).last().unwrap().moves;
+
+    'improve: loop {
+        let boards = play_line(board, &moves_played);
+        let last = boards.len() - 1;
+
+        for size in 2..=window {
+            for start in 0..last.saturating_sub(size - 1) {
+                let (from, to) = (&boards[start], &boards[start + size]);
+                if let Some(segment) = shortest_segment(from, to) {
+                    if verbosity >= Verbosity::Low {
+                        println!(
+                            "Moves {} to {} take {} moves made instead of {}",
+                            start + 1,
+                            start + size,
+                            segment.0 - from.moves,
+                            to.moves - from.moves
+                        );
+                    }
+                    moves_played.splice(start..start + size, segment.1);
+                    continue 'improve;
+                }
+            }
+        }
+
+        if boards[last].completed {
+            for size in 1..=window.min(last) {
+                let from = &boards[last - size];
+                let (ending, _) = depth_first_search(
+                    from.clone(),
+                    boards[last].moves as usize,
+                    1 << 16,
+                    None,
+                    Verbosity::Off,
+                );
+                if !ending.is_partial() && ending.moves < boards[last].moves {
+                    if verbosity >= Verbosity::Low {
+                        println!(
+                            "The last {} moves take {} moves made instead of {}",
+                            size,
+                            ending.moves - from.moves,
+                            boards[last].moves - from.moves
+                        );
+                    }
+                    moves_played.truncate(last - size);
+                    moves_played.extend(ending.moves_played);
+                    continue 'improve;
+                }
+            }
+        }
+
+        break;
+    }
+
+    Optimized {
+        moves_before,
+        moves_after: play_line(board, &moves_played).last().unwrap().moves,
+        moves_played,
+    }
+}
+
+/// The board before the first move, and after every move played
+fn play_line(board: &Board, moves_played: &[usize]) -> Vec<Board> {
+    let mut boards = vec![board.clone()];
+    let mut board = board.clone();
+    for &move_num in moves_played {
+        board.play_move(board.get_moves()[move_num - 1]);
+        boards.push(board.clone());
+    }
+    boards
+}
+
+/// The cheapest way to get from `from` to the position of `to`, if it takes fewer moves made
+///
+/// Only moves that remove cards `to` no longer has are played, which keeps the search small.
+/// Returns the moves made at the end and the moves played.
+fn shortest_segment(from: &Board, to: &Board) -> Option<(i32, Vec<usize>)> {
+    let kept: HashSet<RawCard> = to
+        .remaining_idxs()
+        .iter()
+        .map(|&idx| to.board_cards[idx])
+        .chain(to.stack.iter().copied())
+        .collect();
+
+    let mut segment = Segment {
+        target: to.get_position(),
+        kept,
+        line: vec![],
+        best: None,
+        limit: to.moves,
+    };
+    segment.search(&mut from.clone());
+    segment.best
+}
+
+struct Segment {
+    target: Vec<u8>,
+    /// Cards still there in the target position, which can't be removed on the way
+    kept: HashSet<RawCard>,
+    line: Vec<usize>,
+    best: Option<(i32, Vec<usize>)>,
+    /// Lines that make this many moves or more aren't an improvement
+    limit: i32,
+}
+
+impl Segment {
+    fn search(&mut self, board: &mut Board) {
+        if board.moves >= self.limit || board.completed {
+            return;
+        }
+        if board.get_position() == self.target {
+            self.limit = board.moves;
+            self.best = Some((board.moves, self.line.clone()));
+            return;
+        }
+
+        for (idx, r#move) in board.get_moves().into_iter().enumerate() {
+            let (left, right) = r#move.cards();
+            if self.kept.contains(&left) || right.is_some_and(|right| self.kept.contains(&right)) {
+                continue;
+            }
+            let undo = board.play_move_with_undo(r#move);
+            self.line.push(idx + 1);
+            self.search(board);
+            self.line.pop();
+            board.undo_move(undo);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::{readme_board, solve};
+
+    #[test]
+    fn test_optimize_wasted_draws() {
+        let board = readme_board();
+        // Always play the move needing the most draws
+        let mut moves_played = vec![];
+        let mut played = board.clone();
+        for _ in 0..8 {
+            let moves = played.get_moves();
+            played.play_move(moves[moves.len() - 1]);
+            moves_played.push(moves.len());
+        }
+
+        let optimized = optimize_solution(&board, &moves_played, 4, Verbosity::Off);
+        assert_eq!(optimized.moves_before, played.moves);
+        assert!(optimized.moves_after < optimized.moves_before);
+
+        let after = play_line(&board, &optimized.moves_played).pop().unwrap();
+        assert_eq!(after.moves, optimized.moves_after);
+        assert_eq!(after.get_position(), played.get_position());
+
+        let again = optimize_solution(&board, &optimized.moves_played, 4, Verbosity::Off);
+        assert_eq!(again.moves_after, optimized.moves_after);
+    }
+
+    #[test]
+    fn test_optimize_keeps_shortest_solution() {
+        let board = readme_board();
+        let solution = solve(&board);
+
+        let optimized = optimize_solution(&board, &solution.moves_played, 3, Verbosity::Off);
+        assert_eq!(optimized.moves_before, solution.moves);
+        assert_eq!(optimized.moves_after, solution.moves);
+    }
+}
@@ -0,0 +1,261 @@
+use crate::game::board::Board;
+use crate::game::notation::MoveNotation;
+use crate::game::r#move::Move;
+use crate::solver::advisor::MoveAdvice;
+use crate::solver::config::AbortReason;
+use crate::solver::optimal::OptimalSolutions;
+use crate::solver::optimize::Optimized;
+use crate::solver::stats::SearchStats;
+use serde::Serialize;
+
+/// What goes into the document `json_report` writes besides the moves played
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ReportDetails<'a> {
+    /// Why the search stopped early, if it did
+    pub aborted: Option<AbortReason>,
+    /// What the search did, for searches that keep track of it
+    pub stats: Option<&'a SearchStats>,
+    /// How much `optimize_solution` shortened the moves played, if it was run
+    pub optimized: Option<&'a Optimized>,
+    /// Every shortest solution, when they were searched for
+    pub optimal: Option<&'a OptimalSolutions>,
+}
+
+/// A single JSON document with the deal, the moves of a solution and how the search went
+///
+/// Cards, boards and moves are written through their `Serialize` impls, so cards are
+/// `RawCard` numbers. The document has these fields:
+/// * `deal` - the starting position as a serialized `Board`, with `board`, `stack`,
+///   `stack_position`, `moves_made` and `clear_all`
+/// * `deal_position` - the same position as written by `Board::to_canonical`
+/// * `status` - `"solved"`, `"unsolved"` when the moves don't clear the board, or `"aborted"`
+///   when the search stopped early, with the reason in `abort_reason`
+/// * `moves` - every move as a serialized `Move`, with `match_type`, `draws`, `left` and `right`,
+///   along with the `move_number` it starts at and its `notation`
+/// * `moves_made` - moves made at the end, including draws
+/// * `final` - the position at the end, and the `cards_left` to clear
+/// * `optimized` - `moves_before` and `moves_after` optimizing, or `null` if it wasn't run
+/// * `shortest_solutions` - for a search for every shortest solution, their `moves_made`, the
+///   number of `orderings`, whether the listing was `truncated`, and the moves of every listed
+///   solution in `solutions`, or `null` for other searches
+/// * `stats` - the serialized `SearchStats`, or `null` when a saved solution is replayed
+pub fn json_report(board: &Board, moves_played: &[usize], details: ReportDetails) -> String {
+    let (moves, end) = line_moves(board, moves_played);
+
+    let status = match details.aborted {
+        _ if end.completed => Status::Solved,
+        Some(_) => Status::Aborted,
+        None => Status::Unsolved,
+    };
+
+    let report = Report {
+        deal: board,
+        deal_position: board.to_canonical(),
+        status,
+        abort_reason: details.aborted.filter(|_| status == Status::Aborted),
+        moves,
+        moves_made: end.moves,
+        end: FinalPosition {
+            position: end.to_canonical(),
+            cards_left: end.cards_left(),
+        },
+        optimized: details.optimized.map(|optimized| OptimizedMoves {
+            moves_before: optimized.moves_before,
+            moves_after: optimized.moves_after,
+        }),
+        shortest_solutions: details.optimal.map(|optimal| ShortestSolutions {
+            moves_made: optimal.moves,
+            orderings: optimal.orderings,
+            truncated: optimal.truncated,
+            solutions: optimal
+                .solutions
+                .iter()
+                .map(|solution| line_moves(board, solution).0)
+                .collect(),
+        }),
+        stats: details.stats,
+    };
+    serde_json::to_string(&report).unwrap()
+}
+
+/// A single JSON document with the moves `advise_moves` suggests, best first
+///
+/// The document has an `advice` field with every move as a serialized `Move` along with its
+/// `notation` on `board`, the `samples` it could be played in, the `wins` in those samples and its
+/// `win_rate`, and an `abort_reason` when the searches stopped early, or `null`
+pub fn json_advice(board: &Board, advice: &[MoveAdvice], aborted: Option<AbortReason>) -> String {
+    let advice = Advice {
+        advice: advice
+            .iter()
+            .map(|move_advice| AdvisedMove {
+                r#move: NotatedMove::of(board, move_advice.r#move),
+                samples: move_advice.samples,
+                wins: move_advice.wins,
+                win_rate: move_advice.win_rate(),
+            })
+            .collect(),
+        abort_reason: aborted,
+    };
+    serde_json::to_string(&advice).unwrap()
+}
+
+#[derive(Serialize)]
+struct Report<'a> {
+    deal: &'a Board,
+    deal_position: String,
+    status: Status,
+    abort_reason: Option<AbortReason>,
+    moves: Vec<LineMove>,
+    moves_made: i32,
+    #[serde(rename = "final")]
+    end: FinalPosition,
+    optimized: Option<OptimizedMoves>,
+    shortest_solutions: Option<ShortestSolutions>,
+    stats: Option<&'a SearchStats>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Status {
+    Solved,
+    Unsolved,
+    Aborted,
+}
+
+#[derive(Serialize)]
+struct FinalPosition {
+    position: String,
+    cards_left: usize,
+}
+
+#[derive(Serialize)]
+struct OptimizedMoves {
+    moves_before: i32,
+    moves_after: i32,
+}
+
+#[derive(Serialize)]
+struct ShortestSolutions {
+    moves_made: i32,
+    orderings: u64,
+    truncated: bool,
+    solutions: Vec<Vec<LineMove>>,
+}
+
+#[derive(Serialize)]
+struct Advice {
+    advice: Vec<AdvisedMove>,
+    abort_reason: Option<AbortReason>,
+}
+
+#[derive(Serialize)]
+struct AdvisedMove {
+    #[serde(flatten)]
+    r#move: NotatedMove,
+    samples: usize,
+    wins: usize,
+    win_rate: f64,
+}
+
+#[derive(Serialize)]
+struct LineMove {
+    move_number: i32,
+    #[serde(flatten)]
+    r#move: NotatedMove,
+}
+
+/// A serialized `Move` along with how it's written on the board it's played on
+#[derive(Serialize)]
+struct NotatedMove {
+    #[serde(flatten)]
+    r#move: Move,
+    notation: String,
+}
+
+impl NotatedMove {
+    fn of(board: &Board, r#move: Move) -> NotatedMove {
+        NotatedMove {
+            r#move,
+            notation: MoveNotation::of(board, r#move).to_string(),
+        }
+    }
+}
+
+/// The moves of a line, and the board at the end of it
+fn line_moves(board: &Board, moves_played: &[usize]) -> (Vec<LineMove>, Board) {
+    let mut board = board.clone();
+    let mut moves = vec![];
+    for &move_num in moves_played {
+        let r#move = board.get_moves()[move_num - 1];
+        moves.push(LineMove {
+            move_number: board.moves + 1,
+            r#move: NotatedMove::of(&board, r#move),
+        });
+        board.play_move(r#move);
+    }
+    (moves, board)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::readme_board;
+
+    #[test]
+    fn test_json_report() {
+        let board = readme_board();
+
+        let details = ReportDetails {
+            aborted: Some(AbortReason::TimeLimit),
+            ..ReportDetails::default()
+        };
+        let json = json_report(&board, &[1], details);
+        assert!(json.starts_with("{\"deal\":{\"board\":[10,23,5,"));
+        assert!(json.contains(concat!(
+            ",\"stack_position\":0,\"moves_made\":0,\"clear_all\":false},",
+            "\"deal_position\":\""
+        )));
+        assert!(json.contains(concat!(
+            "\"status\":\"aborted\",\"abort_reason\":\"time_limit\",\"moves\":[{\"move_number\":1,",
+            "\"match_type\":\"board\",\"draws\":0,\"left\":51,\"right\":null,",
+            "\"notation\":\"K R7C6\"}],\"moves_made\":1,"
+        )));
+        assert!(json.contains(
+            "\"cards_left\":27},\"optimized\":null,\"shortest_solutions\":null,\"stats\":null}"
+        ));
+
+        let stats = SearchStats::default();
+        let optimal = OptimalSolutions {
+            moves: 1,
+            orderings: 2,
+            solutions: vec![vec![1]],
+            truncated: true,
+        };
+        let details = ReportDetails {
+            stats: Some(&stats),
+            optimal: Some(&optimal),
+            ..ReportDetails::default()
+        };
+        let json = json_report(&board, &[], details);
+        assert!(json.contains("\"status\":\"unsolved\",\"abort_reason\":null,\"moves\":[],"));
+        assert!(json.contains(concat!(
+            "\"shortest_solutions\":{\"moves_made\":1,\"orderings\":2,\"truncated\":true,",
+            "\"solutions\":[[{\"move_number\":1,\"match_type\":\"board\","
+        )));
+        assert!(json.contains("\"stats\":{\"expanded_per_depth\":[],"));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_json_report_deal_is_a_board() {
+        let board = readme_board();
+
+        let json = json_report(&board, &[1], ReportDetails::default());
+        let report: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let deal: Board = serde_json::from_value(report["deal"].clone()).unwrap();
+
+        assert_eq!(deal.to_canonical(), board.to_canonical());
+        assert_eq!(report["deal_position"], board.to_canonical());
+        assert_eq!(report["deal"], serde_json::to_value(&board).unwrap());
+    }
+}
@@ -0,0 +1,157 @@
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use std::fmt;
+use std::time::Duration;
+
+/// What a search did, and how long it took
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchStats {
+    /// Boards that moves were played from, by move count
+    pub expanded: Vec<u64>,
+    /// Boards reached by playing a move
+    pub moves_played: u64,
+    /// Boards skipped as they were reached before
+    pub duplicates: u64,
+    /// Boards skipped as `Board::is_dead` showed they can't be cleared
+    pub dead_ends: u64,
+    /// Boards skipped as `Board::lower_bound_moves` showed they can't beat the best solution
+    pub over_bound: u64,
+    /// Moves or boards left out by the search's heuristics, like `top_moves` or the beam width
+    pub pruned: u64,
+    /// Most boards waiting in the queue for a single move count
+    pub peak_frontier: usize,
+    /// Highest estimate of the memory taken up by the boards the search stored
+    pub peak_bytes: usize,
+    pub elapsed: Duration,
+}
+
+impl SearchStats {
+    pub fn nodes_expanded(&self) -> u64 {
+        self.expanded.iter().sum()
+    }
+
+    /// Boards expanded per second of wall time
+    pub fn states_per_second(&self) -> f64 {
+        let seconds = self.elapsed.as_secs_f64();
+        if seconds > 0.0 {
+            self.nodes_expanded() as f64 / seconds
+        } else {
+            0.0
+        }
+    }
+
+    pub(crate) fn add_expanded(&mut self, depth: usize, boards: u64) {
+        if self.expanded.len() <= depth {
+            self.expanded.resize(depth + 1, 0);
+        }
+        self.expanded[depth] += boards;
+    }
+
+    pub(crate) fn record_frontier(&mut self, boards: usize, bytes: usize) {
+        self.peak_frontier = self.peak_frontier.max(boards);
+        self.peak_bytes = self.peak_bytes.max(bytes);
+    }
+}
+
+/// Serialized as `expanded_per_depth`, `nodes_expanded`, `moves_played`, `duplicates`,
+/// `dead_ends`, `over_bound`, `pruned`, `peak_frontier`, `peak_bytes`, `wall_time_secs` and
+/// `states_per_second`
+impl Serialize for SearchStats {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut stats = serializer.serialize_struct("SearchStats", 11)?;
+        stats.serialize_field("expanded_per_depth", &self.expanded)?;
+        stats.serialize_field("nodes_expanded", &self.nodes_expanded())?;
+        stats.serialize_field("moves_played", &self.moves_played)?;
+        stats.serialize_field("duplicates", &self.duplicates)?;
+        stats.serialize_field("dead_ends", &self.dead_ends)?;
+        stats.serialize_field("over_bound", &self.over_bound)?;
+        stats.serialize_field("pruned", &self.pruned)?;
+        stats.serialize_field("peak_frontier", &self.peak_frontier)?;
+        stats.serialize_field("peak_bytes", &self.peak_bytes)?;
+        stats.serialize_field("wall_time_secs", &self.elapsed.as_secs_f64())?;
+        stats.serialize_field("states_per_second", &self.states_per_second())?;
+        stats.end()
+    }
+}
+
+impl fmt::Display for SearchStats {
+    /// A summary table, with the boards expanded at every move count
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:>6}  {:>10}", "Depth", "Expanded")?;
+        for (depth, boards) in self.expanded.iter().enumerate() {
+            if *boards > 0 {
+                writeln!(f, "{:>6}  {:>10}", depth, boards)?;
+            }
+        }
+        writeln!(f)?;
+
+        let rows = [
+            ("Boards expanded", self.nodes_expanded().to_string()),
+            ("Moves played", self.moves_played.to_string()),
+            ("Duplicates", self.duplicates.to_string()),
+            ("Dead ends", self.dead_ends.to_string()),
+            ("Over bound", self.over_bound.to_string()),
+            ("Pruned", self.pruned.to_string()),
+            ("Peak frontier", self.peak_frontier.to_string()),
+            (
+                "Peak memory",
+                format!("{:.1} MB", self.peak_bytes as f64 / (1024.0 * 1024.0)),
+            ),
+            ("Wall time", format!("{:.3} s", self.elapsed.as_secs_f64())),
+            (
+                "Boards per second",
+                format!("{:.0}", self.states_per_second()),
+            ),
+        ];
+        for (name, value) in rows {
+            writeln!(f, "{:<18}  {:>12}", name, value)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn get_stats() -> SearchStats {
+        let mut stats = SearchStats {
+            moves_played: 12,
+            duplicates: 3,
+            dead_ends: 2,
+            over_bound: 1,
+            pruned: 4,
+            elapsed: Duration::from_secs(2),
+            ..SearchStats::default()
+        };
+        stats.add_expanded(0, 1);
+        stats.add_expanded(2, 4);
+        stats.add_expanded(2, 1);
+        stats.record_frontier(5, 2048);
+        stats.record_frontier(3, 4096);
+        stats
+    }
+
+    #[test]
+    fn test_search_stats() {
+        let stats = get_stats();
+
+        assert_eq!(stats.expanded, vec![1, 0, 5]);
+        assert_eq!(stats.nodes_expanded(), 6);
+        assert_eq!(stats.states_per_second(), 3.0);
+        assert_eq!(stats.peak_frontier, 5);
+        assert_eq!(stats.peak_bytes, 4096);
+    }
+
+    #[test]
+    fn test_search_stats_json() {
+        assert_eq!(
+            serde_json::to_string(&get_stats()).unwrap(),
+            concat!(
+                "{\"expanded_per_depth\":[1,0,5],\"nodes_expanded\":6,\"moves_played\":12,",
+                "\"duplicates\":3,\"dead_ends\":2,\"over_bound\":1,\"pruned\":4,",
+                "\"peak_frontier\":5,\"peak_bytes\":4096,\"wall_time_secs\":2.0,",
+                "\"states_per_second\":3.0}"
+            )
+        );
+    }
+}
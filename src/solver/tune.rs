@@ -0,0 +1,165 @@
+use crate::game::board::Board;
+use crate::game::utils::{parse_board, Verbosity};
+use crate::solver::bfs::search;
+use crate::solver::config::{Heuristics, Limits, SolverConfig};
+use crate::validators::validate_deal;
+use anyhow::{bail, Context, Result};
+use std::time::{Duration, Instant};
+
+/// How the default search did on a corpus of deals with one setting
+#[derive(Debug, Clone)]
+pub struct TuneResult {
+    pub heuristics: Heuristics,
+    pub deals: usize,
+    pub solved: usize,
+    /// Moves made over all the solved deals
+    pub total_moves: i64,
+    pub elapsed: Duration,
+}
+
+impl TuneResult {
+    pub fn solve_rate(&self) -> f64 {
+        self.solved as f64 / self.deals.max(1) as f64
+    }
+
+    /// Average solution length of the solved deals
+    pub fn average_moves(&self) -> f64 {
+        self.total_moves as f64 / self.solved.max(1) as f64
+    }
+}
+
+/// Read a corpus of deals, one on each line as a board and a stack separated by whitespace
+///
+/// Empty lines and lines starting with # are skipped
+pub fn read_corpus(text: &str) -> Result<Vec<Board>> {
+    let mut deals = vec![];
+    for (idx, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let board = match line.split_whitespace().collect::<Vec<_>>()[..] {
+            [board, stack] => validate_deal(board, stack)
+                .and_then(|_| parse_board(board.to_string(), stack.to_string()))
+                .map(|(board_cards, stack_cards)| {
+                    let leaf_idxs: Vec<usize> = vec![21, 22, 23, 24, 25, 26, 27];
+                    Board::new(board_cards, stack_cards, leaf_idxs, false)
+                })
+                .with_context(|| format!("Line {} isn't a valid deal", idx + 1))?,
+            _ => bail!("Line {} should have a board and a stack", idx + 1),
+        };
+        deals.push(board);
+    }
+    Ok(deals)
+}
+
+/// Run the default search over every deal with each of the settings
+///
+/// Every deal gets at most `time_limit` with each setting. Results are in the same order as the
+/// settings.
+pub fn tune(
+    deals: &[Board],
+    settings: &[Heuristics],
+    max_depth: usize,
+    time_limit: Duration,
+    verbosity: Verbosity,
+) -> Vec<TuneResult> {
+    settings
+        .iter()
+        .map(|&heuristics| {
+            let mut result = TuneResult {
+                heuristics,
+                deals: deals.len(),
+                solved: 0,
+                total_moves: 0,
+                elapsed: Duration::ZERO,
+            };
+
+            for board in deals {
+                let start = Instant::now();
+                let limits = Limits::new(&SolverConfig {
+                    time_limit: Some(time_limit),
+                    ..SolverConfig::default()
+                });
+                let (outcome, _) = search(
+                    board.clone(),
+                    max_depth,
+                    heuristics,
+                    None,
+                    &limits,
+                    Verbosity::Off,
+                );
+                result.elapsed += start.elapsed();
+
+                let solution = outcome.solution();
+                if !solution.is_partial() {
+                    result.solved += 1;
+                    result.total_moves += solution.moves as i64;
+                }
+            }
+
+            if verbosity >= Verbosity::Low {
+                println!(
+                    "Tried {:?} - solved {}/{}",
+                    heuristics, result.solved, result.deals
+                );
+            }
+            result
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_read_corpus() {
+        let deals = read_corpus(
+            "# The deal from the README\n\n  jj6j88a95k3ka02j4q32k0767qk7 68480a55q69a2339527q4490\n",
+        )
+        .unwrap();
+        assert_eq!(deals.len(), 1);
+
+        let err = read_corpus("# Comment\njj6j88a95k3ka02j4q32k0767qk7\n").unwrap_err();
+        assert_eq!(err.to_string(), "Line 2 should have a board and a stack");
+
+        let err = read_corpus("jj6j88a95k3ka02j4q32k0767qk7 68480a55q69a2339527q449a").unwrap_err();
+        assert_eq!(err.to_string(), "Line 1 isn't a valid deal");
+    }
+
+    #[test]
+    fn test_tune() {
+        let deals = read_corpus("jj6j88a95k3ka02j4q32k0767qk7 68480a55q69a2339527q4490").unwrap();
+        let narrow = Heuristics {
+            first_top_moves: 1,
+            first_games: 0,
+            top_moves: 1,
+        };
+        let settings = [Heuristics::DEFAULT, narrow];
+
+        let results = tune(
+            &deals,
+            &settings,
+            60,
+            Duration::from_secs(60),
+            Verbosity::Off,
+        );
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[1].heuristics, narrow);
+        assert_eq!(results[0].solve_rate(), 1.0);
+        assert!(results[0].average_moves() >= 43.0);
+
+        // The deal can't be cleared in fewer than 43 moves
+        let results = tune(
+            &deals,
+            &settings[..1],
+            40,
+            Duration::from_secs(60),
+            Verbosity::Off,
+        );
+        assert_eq!(results[0].solved, 0);
+        assert_eq!(results[0].average_moves(), 0.0);
+    }
+}
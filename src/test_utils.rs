@@ -0,0 +1,43 @@
+//! Boards and solutions the tests share
+use crate::game::board::Board;
+use crate::game::card::RawCard;
+use crate::game::utils::{parse_board, Verbosity};
+use crate::solver::bfs::{search, Solution};
+use crate::solver::config::{Heuristics, Limits};
+
+/// The deal from the README, which the default search solves
+pub(crate) fn readme_board() -> Board {
+    let (board_cards, stack_cards) = parse_board(
+        "jj6j88a95k3ka02j4q32k0767qk7".to_string(),
+        "68480a55q69a2339527q4490".to_string(),
+    )
+    .unwrap();
+    Board::new(board_cards, stack_cards, (21..28).collect(), false)
+}
+
+/// A board with only the top four rows left, the 10 cards given, and the rest of the pyramid
+/// filled in with cards that are already gone
+pub(crate) fn top_rows_board(top_rows: [u8; 10], stack: Vec<RawCard>) -> Board {
+    let mut cards: Vec<RawCard> = top_rows.into_iter().map(RawCard).collect();
+    cards.extend((26..44).map(RawCard));
+    Board::new(cards, stack, vec![6, 7, 8, 9], false)
+}
+
+/// Only the top four rows left, which can be cleared without the stack in 6 moves, where the
+/// first two pairs can be matched in either order
+pub(crate) fn small_board() -> Board {
+    top_rows_board([25, 4, 7, 1, 10, 12, 0, 11, 2, 9], vec![])
+}
+
+/// The line the default search finds for a board, without any limits
+pub(crate) fn solve(board: &Board) -> Solution {
+    let (outcome, _) = search(
+        board.clone(),
+        60,
+        Heuristics::DEFAULT,
+        None,
+        &Limits::default(),
+        Verbosity::Off,
+    );
+    outcome.into_solution()
+}
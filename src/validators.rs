@@ -1,7 +1,11 @@
+use crate::game::blocks::card_blocked_by;
+use crate::game::board::Board;
 use crate::game::card::{Card, RawCard};
-use crate::game::utils::pretty_print_card;
+use crate::game::utils::{card_pos, card_value, match_card, pretty_print_card};
 use anyhow::{bail, Result};
 
+pub use crate::game::utils::validate_position;
+
 pub fn validate_board(board_cards: &[RawCard], stack_cards: &[RawCard]) -> Result<()> {
     let mut card_counts: Vec<u8> = vec![0; 13];
 
@@ -14,24 +18,296 @@ pub fn validate_board(board_cards: &[RawCard], stack_cards: &[RawCard]) -> Resul
         card_counts[card_value as usize] += 1;
     }
 
-    if let Some(idx) =
-        card_counts.iter().enumerate().find_map(
-            |(idx, count)| {
-                if *count != 4 {
-                    Some(idx)
-                } else {
-                    None
-                }
-            },
-        )
-    {
+    let problems = wrong_counts(&card_counts);
+    if !problems.is_empty() {
+        bail!("{}", problems.join("\n"))
+    }
+
+    Ok(())
+}
+
+/// A problem for every rank that isn't present exactly 4 times
+fn wrong_counts(card_counts: &[u8]) -> Vec<String> {
+    card_counts
+        .iter()
+        .enumerate()
+        .filter(|(_, count)| **count != 4)
+        .map(|(idx, count)| {
+            format!(
+                "Card {} is present {} times, but every card needs to be present 4 times across the board and stack",
+                pretty_print_card(RawCard(idx as u8), true),
+                count
+            )
+        })
+        .collect()
+}
+
+/// Check the board and stack of a deal as they were typed, reporting every problem at once
+///
+/// On top of the card counts that `validate_board` checks, characters that aren't cards and a
+/// board or stack of the wrong length are reported, along with the typos that likely caused them.
+pub fn validate_deal(cards_str: &str, stack_str: &str) -> Result<()> {
+    let mut problems: Vec<String> = vec![];
+    let mut card_counts: Vec<u8> = vec![0; 13];
+
+    for (name, string) in [("board", cards_str), ("stack", stack_str)] {
+        for (idx, char) in string.chars().enumerate() {
+            match card_value(char) {
+                Ok(value) => card_counts[value as usize] += 1,
+                Err(_) => problems.push(format!(
+                    "Character {} of the {} ({}) isn't a card{}",
+                    idx + 1,
+                    name,
+                    char,
+                    typo_hint(char)
+                )),
+            }
+        }
+    }
+
+    let board_len = cards_str.chars().count();
+    let stack_len = stack_str.chars().count();
+    if board_len != 28 {
+        problems.push(format!("The board has {} cards, but needs 28", board_len));
+    }
+    if stack_len != 24 {
+        problems.push(format!("The stack has {} cards, but needs 24", stack_len));
+    }
+    if board_len != 28 && board_len + stack_len == 52 {
+        problems.push(format!(
+            "Together they have all 52 cards, so {} cards may have ended up in the wrong one",
+            board_len.abs_diff(28)
+        ));
+    }
+
+    problems.extend(wrong_counts(&card_counts));
+
+    // 10 typed as 10 is read as an Ace followed by a 10, and typed as 1 it's read as an Ace
+    let (aces, tens) = (card_counts[0], card_counts[9]);
+    let mut tens_typed_as_10 = false;
+    for (name, string) in [("board", cards_str), ("stack", stack_str)] {
+        let chars: Vec<char> = string.chars().collect();
+        for (idx, pair) in chars.windows(2).enumerate() {
+            if aces > 4 && pair == ['1', '0'] {
+                tens_typed_as_10 = true;
+                problems.push(format!(
+                    "The 10 at character {} of the {} is read as an Ace and a 10, use 0 for 10",
+                    idx + 1,
+                    name
+                ));
+            }
+        }
+    }
+    let over: Vec<usize> = (0..13).filter(|&idx| card_counts[idx] > 4).collect();
+    let under: Vec<usize> = (0..13).filter(|&idx| card_counts[idx] < 4).collect();
+    if aces > 4 && tens < 4 && !tens_typed_as_10 {
+        problems.push(format!(
+            "There are {} Aces too many and {} 10s too few, a 10 may have been entered as 1 instead of 0",
+            aces - 4,
+            4 - tens
+        ));
+    } else if let ([over], [under]) = (&over[..], &under[..]) {
+        if card_counts[*over] - 4 == 4 - card_counts[*under] && !tens_typed_as_10 {
+            problems.push(format!(
+                "A {} may have been entered as a {}",
+                pretty_print_card(RawCard(*under as u8), true),
+                pretty_print_card(RawCard(*over as u8), true),
+            ));
+        }
+    }
+
+    if !problems.is_empty() {
+        bail!("The deal isn't valid:\n    {}", problems.join("\n    "))
+    }
+
+    Ok(())
+}
+
+/// A suggestion for characters that look like they were meant to be a card
+fn typo_hint(char: char) -> &'static str {
+    match char {
+        'o' | 'O' => ", did you mean 0 or t for 10?",
+        'l' | 'i' | 'I' => ", did you mean 1 for Ace?",
+        's' | 'S' => ", did you mean 5?",
+        'z' | 'Z' => ", did you mean 2?",
+        'g' | 'G' => ", did you mean 9?",
+        ' ' => ", leave out the spaces",
+        _ => "",
+    }
+}
+
+/// Find the cards that are missing from a deal where some of the stack is still unknown
+///
+/// Every rank needs to be present 4 times across the board and stack, so whatever isn't known yet
+/// has to be among the unknown stack cards. The missing cards are numbered the same way as
+/// `parse_board` would, following the known copies of the same rank.
+pub fn missing_cards(
+    board_cards: &[RawCard],
+    stack_cards: &[Option<RawCard>],
+) -> Result<Vec<RawCard>> {
+    let mut card_counts: Vec<u8> = vec![0; 13];
+
+    for card in board_cards.iter().chain(stack_cards.iter().flatten()) {
+        let card_value = Card::from(*card).0 - 1;
+        card_counts[card_value as usize] += 1;
+    }
+
+    if let Some(idx) = card_counts.iter().position(|count| *count > 4) {
         let card = RawCard(idx.try_into().unwrap());
         bail!(
-            "Card {} is present {} times, but every card needs to be present 4 times across the board and stack",
+            "Card {} is present {} times, but every card can only be present 4 times across the board and stack",
             pretty_print_card(card, true),
             card_counts[idx]
         )
     }
 
+    let missing: Vec<RawCard> = card_counts
+        .iter()
+        .enumerate()
+        .flat_map(|(idx, count)| (*count..4).map(move |copy| RawCard(idx as u8 + copy * 13)))
+        .collect();
+
+    let unknown = stack_cards.iter().filter(|card| card.is_none()).count();
+    if missing.len() != unknown {
+        bail!(
+            "There are {} unknown cards in the stack, but {} cards are missing from the board and stack",
+            unknown,
+            missing.len()
+        )
+    }
+
+    Ok(missing)
+}
+
+/// Look for reasons a deal can never be solved, before spending any time searching
+///
+/// Fails with every reason found. A deal passing doesn't mean it can be solved, only that none of
+/// these reasons apply.
+pub fn analyze_deal(
+    board_cards: &[RawCard],
+    stack_cards: &[RawCard],
+    clear_all: bool,
+) -> Result<()> {
+    let leaf_idxs: Vec<usize> = vec![21, 22, 23, 24, 25, 26, 27];
+    let board = Board::new(
+        board_cards.to_vec(),
+        stack_cards.to_vec(),
+        leaf_idxs,
+        clear_all,
+    );
+    let mut reasons: Vec<String> = vec![];
+
+    // Cards on the board where every partner covers them or is covered by them
+    for (idx, &card) in board_cards.iter().enumerate().take(28) {
+        let partner = match_card(card.into());
+        if partner.0 == 13 || stack_cards.iter().any(|&c| Card::from(c) == partner) {
+            continue;
+        }
+
+        let can_be_matched = board_cards.iter().enumerate().any(|(other, &other_card)| {
+            Card::from(other_card) == partner
+                && !card_blocked_by(idx).contains(&other)
+                && !card_blocked_by(other).contains(&idx)
+        });
+        if !can_be_matched {
+            reasons.push(format!(
+                "The {} {} can't be matched, as every {} either covers it or is covered by it",
+                pretty_print_card(card, true),
+                card_pos(&board, card),
+                pretty_print_card(RawCard(partner.0 - 1), true),
+            ));
+        }
+    }
+
+    // Cards that all have a partner on their own, but not enough partners to go around
+    if reasons.is_empty() {
+        if let Some(rank) = board.dead_rank() {
+            reasons.push(format!(
+                "The {}s and {}s can't all be paired up, as too many of them cover each other",
+                pretty_print_card(RawCard(rank - 1), true),
+                pretty_print_card(RawCard(12 - rank), true),
+            ));
+        }
+    }
+
+    if !reasons.is_empty() {
+        bail!("The deal can't be solved:\n    {}", reasons.join("\n    "))
+    }
+
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::game::utils::parse_board;
+
+    #[test]
+    fn test_analyze_deal() {
+        let (board_cards, stack_cards) = parse_board(
+            "jj6j88a95k3ka02j4q32k0767qk7".to_string(),
+            "68480a55q69a2339527q4490".to_string(),
+        )
+        .unwrap();
+
+        assert!(analyze_deal(&board_cards, &stack_cards, false).is_ok());
+    }
+
+    #[test]
+    fn test_analyze_deal_unmatchable_card() {
+        // A 3 at the top of the pyramid, with all the 10s below it
+        let (board_cards, stack_cards) = parse_board(
+            "30000aaaa2222444455556667777".to_string(),
+            "333688889999jjjjqqqqkkkk".to_string(),
+        )
+        .unwrap();
+        validate_board(&board_cards, &stack_cards).unwrap();
+
+        let error = analyze_deal(&board_cards, &stack_cards, false)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("on board 1st row, 1st card can't be matched"));
+    }
+
+    #[test]
+    fn test_validate_deal() {
+        assert!(validate_deal("jj6j88a95k3ka02j4q32k0767qk7", "68480a55q69a2339527q4490").is_ok());
+        // 10 can be entered as t, like it can in deal files
+        assert!(validate_deal("jj6j88a95k3kaT2j4q32kt767qk7", "68480a55q69a2339527q4490").is_ok());
+
+        // Every problem is reported at once
+        let error = validate_deal("jj6j88a95k3ka02j4q32k0767qk7x", "68480a55q69a2339527q449")
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("Character 29 of the board (x) isn't a card"));
+        assert!(error.contains("The board has 29 cards, but needs 28"));
+        assert!(error.contains("The stack has 23 cards, but needs 24"));
+        assert!(error.contains("present 3 times"));
+    }
+
+    #[test]
+    fn test_validate_deal_typos() {
+        // The last 10 of the stack entered as 10
+        let error = validate_deal("jj6j88a95k3ka02j4q32k0767qk7", "68480a55q69a2339527q44910")
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("The 10 at character 24 of the stack is read as an Ace and a 10"));
+
+        // And entered as 1
+        let error = validate_deal("jj6j88a95k3ka02j4q32k0767qk7", "68480a55q69a2339527q4491")
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("a 10 may have been entered as 1 instead of 0"));
+
+        // A 7 entered as a 6
+        let error = validate_deal("jj6j88a95k3ka02j4q32k0767qk6", "68480a55q69a2339527q4490")
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("7 may have been entered as a 6"));
+
+        let error = validate_deal("jj6j88a95k3ka02j4q32k0767qkO", "68480a55q69a2339527q4490")
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("(O) isn't a card, did you mean 0 or t for 10?"));
+    }
+}